@@ -19,24 +19,65 @@ struct PatternData {
     tags: Vec<&'static str>,
     #[serde(skip_deserializing)]
     uses_non_standard_regex: bool,
+    backtrack_limit: Option<usize>,
+    validator: Option<String>,
 }
 
+// Must match `DEFAULT_BACKTRACK_LIMIT` in src/regex_pd.rs
+const DEFAULT_BACKTRACK_LIMIT: usize = 100_000;
+
 
 impl fmt::Debug for PatternData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("PatternData")
-            .field("name", &self.name)
+            .field("name", &Borrowed(&self.name))
             .field("plural_name", &self.plural_name)
-            .field("description", &self.description)
-            .field("exploit", &self.exploit)
+            .field("description", &self.description.map(Borrowed))
+            .field("exploit", &self.exploit.as_deref().map(Borrowed))
             .field("rarity", &self.rarity)
-            .field("url", &self.url)
-            .field("tags", &self.tags)
-            // .field("uses_non_standard_regex", &self.uses_non_standard_regex) TODO: use it
+            .field("url", &self.url.map(Borrowed))
+            .field("tags", &BorrowedSlice(&self.tags))
+            .field("uses_non_standard_regex", &self.uses_non_standard_regex)
+            .field("validator", &self.validator.as_deref().map(ValidatorVariant))
             .finish()
     }
 }
 
+/// Prints a string as a `Cow::Borrowed` literal.
+struct Borrowed<'a>(&'a str);
+
+impl fmt::Debug for Borrowed<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cow::Borrowed({:?})", self.0)
+    }
+}
+
+/// Prints strings as a `Cow::Borrowed` slice of `Cow::Borrowed` literals.
+struct BorrowedSlice<'a>(&'a [&'a str]);
+
+impl fmt::Debug for BorrowedSlice<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let strings: Vec<Borrowed> = self.0.iter().map(|s| Borrowed(s)).collect();
+        write!(f, "Cow::Borrowed(&{:?})", strings)
+    }
+}
+
+/// Prints a `"snake_case"` validator name as its `Validator::CamelCase` variant.
+struct ValidatorVariant<'a>(&'a str);
+
+impl fmt::Debug for ValidatorVariant<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Validator::")?;
+        for word in self.0.split('_') {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                write!(f, "{}{}", first.to_ascii_uppercase(), chars.as_str())?;
+            }
+        }
+        Ok(())
+    }
+}
+
 fn main() {
     let mut data: Vec<PatternData> = serde_json::from_str(include_str!("./data/regex.json")).unwrap();
 
@@ -49,60 +90,70 @@ fn main() {
             .expect("can't compile for regex_no_anchor")
             .replace(&d.regex_no_anchor, "")
             .to_string();
-        match Regex::new(&d.regex) {
-            Ok(_) => {
-                d.uses_non_standard_regex = false;
-            }
-            Err(_) => {
-                d.uses_non_standard_regex = true;
-            }
-        }
+        d.uses_non_standard_regex =
+            Regex::new(&d.regex).is_err() || Regex::new(&d.regex_no_anchor).is_err();
     });
 
     data.sort_by(|a, b| {
         a.name.cmp(&b.name)
     });
 
-    // TODO support non standard regex, currently crashes sometimes if we dont filter
-    data.retain(|r| Regex::new(&r.regex).is_ok() && Regex::new(&r.regex_no_anchor).is_ok());
+    // Patterns the regex crate can't compile run on fancy-regex instead,
+    // only drop the ones neither of them supports
+    data.retain(|r| {
+        let supported = Fancy::new(&r.regex).is_ok() && Fancy::new(&r.regex_no_anchor).is_ok();
+        if !supported {
+            println!("cargo:warning=Skipping pattern {:?}, its regex is not supported", r.name);
+        }
+        supported
+    });
 
-    let mut data_str = format!("{:?}", data);
-    // we want reference to [], i.e. &[]
-    data_str = data_str.replace("tags: [", "tags: &[");
+    let data_str = format!("{:?}", data);
 
     let regex_str: String = data.iter().fold(String::new(), |mut output, d| {
-        let _ = write!(
-            output,
-            "\tLazy::new(|| Regex::new({:?}).unwrap()),\n",
-            d.regex
-        );
+        let _ = writeln!(output, "\t{},", pattern_regex(d, &d.regex));
+        output
+    });
+
+    // `^` and `$` also match at line breaks, for text made of several lines
+    let regex_lines_str: String = data.iter().fold(String::new(), |mut output, d| {
+        let _ = writeln!(output, "\t{},", pattern_regex(d, &format!("(?m){}", d.regex)));
         output
     });
 
     let regex_no_anchor_str: String = data.iter().fold(String::new(), |mut output, d| {
-        let _ = write!(
-            output,
-            "\tLazy::new(|| Regex::new({:?}).unwrap()),\n",
-            d.regex_no_anchor
-        );
+        let _ = writeln!(output, "\t{},", pattern_regex(d, &d.regex_no_anchor));
         output
     });
 
     let count = data.len();
     let final_str = format!(
-        "pub const PATTERN_DATA: [PatternData; {count}] = {data_str};"
+        "pub static PATTERN_DATA: [PatternData; {count}] = {data_str};"
     );
     let out_dir = env::var_os("OUT_DIR").unwrap();
     let dest_path = Path::new(&out_dir).join("pattern_data.rs");
     fs::write(dest_path, final_str).unwrap();
 
     let mut final_str = format!(
-        "pub static REGEX: [Lazy<Regex>; {count}] = [\n{regex_str}];\n"
+        "pub static REGEX: [Lazy<PatternRegex>; {count}] = [\n{regex_str}];\n"
     );
     final_str += "\n";
     final_str += format!(
-        "pub static REGEX_NO_ANCHOR: [Lazy<Regex>; {count}] = [\n{regex_no_anchor_str}];"
+        "pub static REGEX_LINES: [Lazy<PatternRegex>; {count}] = [\n{regex_lines_str}];\n"
+    ).as_str();
+    final_str += "\n";
+    final_str += format!(
+        "pub static REGEX_NO_ANCHOR: [Lazy<PatternRegex>; {count}] = [\n{regex_no_anchor_str}];"
     ).as_str();
     let regex_dest_path = Path::new(&out_dir).join("regex_data.rs");
     fs::write(regex_dest_path, final_str).unwrap();
 }
+
+fn pattern_regex(d: &PatternData, regex: &str) -> String {
+    if d.uses_non_standard_regex {
+        let backtrack_limit = d.backtrack_limit.unwrap_or(DEFAULT_BACKTRACK_LIMIT);
+        format!("Lazy::new(|| PatternRegex::fancy({regex:?}, {backtrack_limit}).unwrap())")
+    } else {
+        format!("Lazy::new(|| PatternRegex::standard({regex:?}).unwrap())")
+    }
+}
@@ -13,16 +13,40 @@ const HELP_TEMPLATE_FORMAT: &str = "\
 {all-args}{after-help}
 ";
 
+const EXIT_STATUS_HELP: &str = "\
+Exit status:
+  0  nothing was found
+  1  matches were found, or only matches meeting a --fail-on condition
+  2  an error occurred";
+
 pub fn cli() -> Command {
     Command::new(env!("CARGO_PKG_NAME"))
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
         .about(env!("CARGO_PKG_DESCRIPTION"))
         .help_template(HELP_TEMPLATE_FORMAT)
+        .after_help(EXIT_STATUS_HELP)
         .arg(
             Arg::new("input")
-                .help("Input to identify. Input can be text, a file or directory.")
-                .required(false),
+                .help("Inputs to identify. An input can be text, a file or directory. \
+                '-' reads text from the standard input.")
+                .required(false)
+                .num_args(1..)
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("files-from")
+                .long("files-from")
+                .value_name("FILE")
+                .help("Also identify the files and directories listed in FILE, one per line. \
+                '-' reads the list from the standard input."),
+        )
+        .arg(
+            Arg::new("null-input")
+                .short('0')
+                .long("null-input")
+                .help("Paths in the --files-from list are separated by NUL, as printed by 'find -print0'.")
+                .action(clap::ArgAction::SetTrue),
         )
         .arg(
             Arg::new("tags")
@@ -31,6 +55,15 @@ pub fn cli() -> Command {
                 .help("Show available tags and exit.")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("patterns")
+                .short('p')
+                .long("patterns")
+                .value_name("FILE")
+                .help("Load additional patterns from a JSON file in the same format as regex.json. \
+                Can be given multiple times.")
+                .action(clap::ArgAction::Append),
+        )
         .arg(
             Arg::new("rarity")
                 .short('r')
@@ -52,6 +85,66 @@ pub fn cli() -> Command {
                 .help("Exclude matches with these tags.")
                 .default_value(""),
         )
+        .arg(
+            Arg::new("decode")
+                .long("decode")
+                .help("Also identify text decoded from base64, hex and percent-encoded parts \
+                of the input, including gzip compressed base64.")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("decode-depth")
+                .long("decode-depth")
+                .value_name("DEPTH")
+                .help("How many layers of encoding --decode looks through.")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("3"),
+        )
+        .arg(
+            Arg::new("min-length")
+                .long("min-length")
+                .value_name("LENGTH")
+                .help("Shortest string extracted from binary files, text files are scanned whole.")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("4"),
+        )
+        .arg(
+            Arg::new("validated-only")
+                .long("validated-only")
+                .help("Only show matches with a valid checksum, \
+                e.g. card numbers passing the Luhn check.")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("protocol")
+                .long("protocol")
+                .value_name("PROTOCOLS")
+                .help("Only scan packets of captures using these comma separated protocols, \
+                any of tcp, udp, icmp, icmpv6 and ip.")
+                .default_value(""),
+        )
+        .arg(
+            Arg::new("port")
+                .long("port")
+                .value_name("PORTS")
+                .help("Only scan packets of captures from or to these comma separated ports.")
+                .default_value(""),
+        )
+        .arg(
+            Arg::new("host")
+                .long("host")
+                .value_name("ADDRESSES")
+                .help("Only scan packets of captures from or to these comma separated IP addresses.")
+                .default_value(""),
+        )
+        .arg(
+            Arg::new("fail-on")
+                .long("fail-on")
+                .value_name("CONDITION")
+                .help("Only exit with status 1 for matches meeting CONDITION, \
+                either 'rarity:<min>' or 'tag:<tag>'. Can be given multiple times.")
+                .action(clap::ArgAction::Append),
+        )
         .arg(
             Arg::new("only_text")
                 .short('o')
@@ -59,6 +152,86 @@ pub fn cli() -> Command {
                 .help("Do not scan files or folders.")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("no-ignore")
+                .long("no-ignore")
+                .help("Also scan files excluded by .gitignore, .ignore and similar files in directories.")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("hidden")
+                .long("hidden")
+                .help("Also scan hidden files and directories in directories.")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("glob")
+                .short('g')
+                .long("glob")
+                .value_name("GLOB")
+                .help("Only scan files in directories matching GLOB. Can be given multiple times.")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("exclude-glob")
+                .long("exclude-glob")
+                .value_name("GLOB")
+                .help("Skip files and directories matching GLOB. Can be given multiple times.")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("max-depth")
+                .long("max-depth")
+                .value_name("NUM")
+                .value_parser(clap::value_parser!(usize))
+                .help("Only descend NUM directories deep into directories."),
+        )
+        .arg(
+            Arg::new("follow")
+                .short('L')
+                .long("follow")
+                .help("Follow symbolic links in directories.")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("max-filesize")
+                .long("max-filesize")
+                .value_name("SIZE")
+                .help("Skip files in directories larger than SIZE bytes, \
+                SIZE may end with K, M or G, e.g. 10M."),
+        )
+        .arg(
+            Arg::new("archive-depth")
+                .long("archive-depth")
+                .value_name("DEPTH")
+                .help("Look into zip and tar archives and gzip, bzip2, xz and zstd files \
+                nested up to DEPTH deep, 0 scans them as they are.")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("4"),
+        )
+        .arg(
+            Arg::new("max-decompressed")
+                .long("max-decompressed")
+                .value_name("SIZE")
+                .help("Stop decompressing an archive after SIZE bytes in total, e.g. 500M.")
+                .default_value("1G"),
+        )
+        .arg(
+            Arg::new("max-ratio")
+                .long("max-ratio")
+                .value_name("RATIO")
+                .help("Skip files in archives that decompress to more than RATIO times their size.")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("200"),
+        )
+        .arg(
+            Arg::new("threads")
+                .short('j')
+                .long("threads")
+                .value_name("NUM")
+                .value_parser(clap::value_parser!(usize))
+                .help("Number of threads used for scanning. Defaults to the number of CPUs."),
+        )
         .arg(
             Arg::new("disable-borderless")
                 .short('d')
@@ -69,8 +242,23 @@ pub fn cli() -> Command {
         .arg(
             Arg::new("format")
                 .long("format")
-                .help("Output format.")
-                .value_parser(["default", "json", "pretty"]),
+                .help("Output format. \
+                'raw' prints one uncolored 'source:line:column:name:matched:details' line per match, \
+                'sarif' a SARIF 2.1.0 log for code-scanning tools.")
+                .value_parser(["default", "json", "pretty", "raw", "sarif"]),
+        )
+        .arg(
+            Arg::new("separator")
+                .long("separator")
+                .value_name("SEP")
+                .default_value(":")
+                .help("Field separator of the raw output format."),
+        )
+        .arg(
+            Arg::new("null")
+                .long("null")
+                .help("Terminate raw output records with NUL instead of a newline.")
+                .action(clap::ArgAction::SetTrue),
         )
         .arg(
             Arg::new("reverse")
@@ -111,30 +299,52 @@ pub fn cli() -> Command {
                 .about("Analyze a pcapng file.\nOption of super command apply.")
                 .arg(
                     Arg::new("input")
-                        .help("pcapng input file.")
-                        .required(true),
+                        .help("pcapng input files.")
+                        .required(true)
+                        .num_args(1..),
                 )
+                .arg(export_pcap_arg())
+                .arg(export_flows_arg())
         )
         .subcommand(
             Command::new("pcap")
                 .about("Analyze a pcap file.\nOption of super command apply.")
                 .arg(
                     Arg::new("input")
-                        .help("pcapng input file.")
-                        .required(true),
+                        .help("pcap input files.")
+                        .required(true)
+                        .num_args(1..),
                 )
+                .arg(export_pcap_arg())
+                .arg(export_flows_arg())
         )
 }
 
+fn export_pcap_arg() -> Arg {
+    Arg::new("export-pcap")
+        .long("export-pcap")
+        .value_name("FILE")
+        .help("Write the packets that matches were found in to a new pcapng FILE, \
+        each with a comment naming the patterns that matched.")
+}
+
+fn export_flows_arg() -> Arg {
+    Arg::new("export-flows")
+        .long("export-flows")
+        .help("Export all packets of the connections matches were found in, not just the matching ones.")
+        .requires("export-pcap")
+        .action(clap::ArgAction::SetTrue)
+}
+
 fn print_completions<G: Generator>(gen: G, cmd: &mut Command) {
     generate(gen, cmd, cmd.get_name().to_string(), &mut io::stdout());
 }
 
-pub fn generate_completions(generator: &String) {
+pub fn generate_completions(generator: &str) {
     let mut cmd = cli();
     eprintln!("Generating completion file for {generator}...");
 
-    match generator.as_str() {
+    match generator {
         "bash" => print_completions(Bash, &mut cmd),
         "zsh" => print_completions(Zsh, &mut cmd),
         "fish" => print_completions(Fish, &mut cmd),
@@ -142,5 +352,4 @@ pub fn generate_completions(generator: &String) {
         "elvish" => print_completions(Elvish, &mut cmd),
         _ => eprintln!("Unknown shell specified."),
     }
-    return;
-}
\ No newline at end of file
+}
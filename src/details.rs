@@ -0,0 +1,205 @@
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use serde::Serialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What a match decodes to, for kinds of matches that carry more than their name tells.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Details {
+    Jwt {
+        header: Value,
+        claims: Value,
+        /// Signs of a weak or unusable token, `alg: none`, `expired` or `not yet valid`.
+        flags: Vec<String>,
+    },
+    /// Unix timestamp in seconds or milliseconds.
+    Timestamp { utc: String },
+    Uuid {
+        version: u8,
+        variant: &'static str,
+        /// Creation time of time-based UUIDs (versions 1, 6 and 7).
+        time: Option<String>,
+    },
+    Ulid { time: String },
+    /// MongoDB ObjectID.
+    ObjectId { time: String },
+    /// Credentials of an HTTP `Authorization: Basic` header.
+    BasicAuth { user: String, password: String },
+}
+
+impl fmt::Display for Details {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Details::Jwt { header, claims, flags } => {
+                write!(f, "header {} claims {}", header, claims)?;
+                if !flags.is_empty() {
+                    write!(f, " [{}]", flags.join(", "))?;
+                }
+                Ok(())
+            }
+            Details::Timestamp { utc } => write!(f, "{}", utc),
+            Details::Uuid { version, variant, time } => {
+                write!(f, "version {}, {} variant", version, variant)?;
+                if let Some(time) = time {
+                    write!(f, ", created {}", time)?;
+                }
+                Ok(())
+            }
+            Details::Ulid { time } | Details::ObjectId { time } => write!(f, "created {}", time),
+            Details::BasicAuth { user, password } => {
+                write!(f, "user {:?}, password {:?}", user, password)
+            }
+        }
+    }
+}
+
+/// Details of `matched_on` depending on the tags of the pattern it matched,
+/// `None` for patterns without details or if `matched_on` doesn't decode.
+pub(crate) fn details(tags: &[Cow<str>], matched_on: &str) -> Option<Details> {
+    let has = |tag: &str| tags.iter().any(|t| t == tag);
+    if has("JWT") {
+        jwt(matched_on)
+    } else if has("UNIX Timestamp") {
+        timestamp(matched_on)
+    } else if has("UUID") {
+        uuid(matched_on)
+    } else if has("ULID") {
+        ulid(matched_on)
+    } else if has("ObjectID") {
+        object_id(matched_on)
+    } else if has("Basic Authentication") {
+        basic_auth(matched_on)
+    } else {
+        None
+    }
+}
+
+fn jwt(token: &str) -> Option<Details> {
+    let mut parts = token.split('.');
+    let mut decode_part = || -> Option<Value> {
+        let part = parts.next()?.trim_end_matches('=');
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(part).ok()?).ok()
+    };
+    let header = decode_part()?;
+    let claims = decode_part()?;
+
+    let mut flags = Vec::new();
+    if header["alg"].as_str().is_some_and(|alg| alg.eq_ignore_ascii_case("none")) {
+        flags.push(String::from("alg: none"));
+    }
+    let now = now_seconds();
+    if claims["exp"].as_f64().is_some_and(|exp| exp < now) {
+        flags.push(String::from("expired"));
+    }
+    if claims["nbf"].as_f64().is_some_and(|nbf| nbf > now) {
+        flags.push(String::from("not yet valid"));
+    }
+    Some(Details::Jwt { header, claims, flags })
+}
+
+fn timestamp(timestamp: &str) -> Option<Details> {
+    let number: i64 = timestamp.parse().ok()?;
+    // the millisecond patterns match 11 to 13 digits
+    let millis = if timestamp.len() > 10 { number } else { number.checked_mul(1000)? };
+    Some(Details::Timestamp { utc: format_utc(millis) })
+}
+
+fn uuid(uuid: &str) -> Option<Details> {
+    let hex: String = uuid.chars().filter(|&c| c != '-').collect();
+    // custom patterns tagged UUID may match anything
+    if hex.len() != 32 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let value = u128::from_str_radix(&hex, 16).ok()?;
+    // the hex digits in `range`, counted from the left
+    let field = |range: std::ops::Range<u32>| -> u64 {
+        let bits = 4 * (range.end - range.start);
+        (value >> (128 - 4 * range.end) & ((1 << bits) - 1)) as u64
+    };
+
+    let version = field(12..13) as u8;
+    let variant = match field(16..17) {
+        0..=7 => "NCS",
+        8..=11 => "RFC 9562",
+        12..=13 => "Microsoft",
+        _ => "reserved",
+    };
+    // 100 nanosecond intervals since the Gregorian calendar started, 1582-10-15
+    const GREGORIAN_OFFSET: i64 = 0x01B21DD213814000;
+    let time = match version {
+        1 => {
+            let ticks = field(13..16) << 48 | field(8..12) << 32 | field(0..8);
+            Some((ticks as i64 - GREGORIAN_OFFSET) / 10_000)
+        }
+        6 => {
+            let ticks = field(0..8) << 28 | field(8..12) << 12 | field(13..16);
+            Some((ticks as i64 - GREGORIAN_OFFSET) / 10_000)
+        }
+        7 => Some(field(0..12) as i64),
+        _ => None,
+    };
+    Some(Details::Uuid { version, variant, time: time.map(format_utc) })
+}
+
+fn ulid(ulid: &str) -> Option<Details> {
+    const CROCKFORD_BASE32: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+    // the first 10 characters are the milliseconds since the epoch
+    let millis = ulid.get(..10)?.bytes().try_fold(0i64, |millis, c| {
+        let value = CROCKFORD_BASE32.iter().position(|&b| b == c.to_ascii_uppercase())?;
+        Some(millis << 5 | value as i64)
+    })?;
+    Some(Details::Ulid { time: format_utc(millis) })
+}
+
+fn object_id(object_id: &str) -> Option<Details> {
+    // the first 4 bytes are the seconds since the epoch
+    let seconds = i64::from_str_radix(object_id.get(..8)?, 16).ok()?;
+    Some(Details::ObjectId { time: format_utc(seconds * 1000) })
+}
+
+fn basic_auth(header: &str) -> Option<Details> {
+    let encoded = header.split_whitespace().last()?;
+    let credentials = String::from_utf8(STANDARD.decode(encoded).ok()?).ok()?;
+    // passwords may contain colons, user names may not
+    let (user, password) = credentials.split_once(':')?;
+    Some(Details::BasicAuth { user: user.to_string(), password: password.to_string() })
+}
+
+fn now_seconds() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |d| d.as_secs_f64())
+}
+
+/// RFC 3339 UTC time of `millis` milliseconds since the epoch, e.g. `2020-01-01T00:00:00.000Z`.
+fn format_utc(millis: i64) -> String {
+    format_date_time(millis.div_euclid(1000), &format!("{:03}", millis.rem_euclid(1000)))
+}
+
+/// Like [`format_utc`] with microseconds, e.g. `2020-01-01T00:00:00.000000Z`.
+pub(crate) fn format_utc_micros(seconds: i64, micros: u32) -> String {
+    format_date_time(seconds, &format!("{:06}", micros))
+}
+
+fn format_date_time(seconds: i64, fraction: &str) -> String {
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+
+    // days to civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{}Z",
+        year, month, day, time / 3600, time % 3600 / 60, time % 60, fraction
+    )
+}
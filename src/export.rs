@@ -0,0 +1,175 @@
+//! Writing the packets of captures that matches were found in to a new pcapng file.
+
+use crate::identifier::capture::{read_capture, Frame};
+use crate::identifier::packet::{dissect, Protocol};
+use crate::identifier::{Match, Source};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x00000001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x00000006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B3C4D;
+
+const OPT_END_OF_OPT: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const SHB_USER_APPL: u16 = 4;
+
+/// A connection regardless of its direction, `(protocol, endpoint, endpoint)`
+/// with the lower endpoint first.
+type Flow = (Protocol, (IpAddr, Option<u16>), (IpAddr, Option<u16>));
+
+fn flow(protocol: Protocol, src: (IpAddr, Option<u16>), dst: (IpAddr, Option<u16>)) -> Flow {
+    if src <= dst { (protocol, src, dst) } else { (protocol, dst, src) }
+}
+
+/// Writes the packets of captures that `matches` were found in to a pcapng file at `output`,
+/// in the order of the captures and the packets in them.
+///
+/// With `whole_flows`, all packets of the TCP connections and UDP and ICMP flows
+/// the matches were found in are written. Packets with matches carry a comment
+/// for each pattern that matched. Returns the number of packets written.
+pub fn export_capture(matches: &[Match], output: &Path, whole_flows: bool) -> anyhow::Result<usize> {
+    // patterns that matched, by capture and packet number
+    let mut captures: Vec<(&PathBuf, HashMap<usize, Vec<&str>>)> = Vec::new();
+    let mut flows: HashSet<(&PathBuf, Flow)> = HashSet::new();
+    for m in matches {
+        let Source::Packet { path, index } = &m.location.source else {
+            continue;
+        };
+        let packets = match captures.iter().position(|(capture, _)| *capture == path) {
+            Some(i) => &mut captures[i].1,
+            None => {
+                captures.push((path, HashMap::new()));
+                &mut captures.last_mut().unwrap().1
+            }
+        };
+        let names = packets.entry(*index).or_default();
+        if !names.contains(&m.name.as_str()) {
+            names.push(&m.name);
+        }
+        if let Some(packet) = &m.packet {
+            if let (Some(protocol), Some(src), Some(dst)) = (packet.protocol, packet.src, packet.dst) {
+                flows.insert((path, flow(protocol, (src, packet.src_port), (dst, packet.dst_port))));
+            }
+        }
+    }
+
+    for (path, _) in &captures {
+        if output.exists() && output.canonicalize()? == path.canonicalize()? {
+            anyhow::bail!("Exporting to {:?} would overwrite the capture it exports from", output);
+        }
+    }
+    let file = File::create(output)
+        .map_err(|e| anyhow::anyhow!("Failed to create {:?}: {}", output, e))?;
+    let mut writer = PcapNgWriter::new(BufWriter::new(file))?;
+
+    for (path, packets) in &captures {
+        let mut result = Ok(());
+        read_capture(path, |frame| {
+            if result.is_err() {
+                return;
+            }
+            let names = packets.get(&frame.number);
+            let in_flow = whole_flows && dissect(frame.linktype, frame.data).is_some_and(|d| {
+                let key = flow(d.protocol, (d.src, d.src_port), (d.dst, d.dst_port));
+                flows.contains(&(*path, key))
+            });
+            if names.is_some() || in_flow {
+                result = writer.write_packet(path, frame, names.map_or(&[], Vec::as_slice));
+            }
+        })?;
+        result.map_err(|e| anyhow::anyhow!("Failed to write {:?}: {}", output, e))?;
+    }
+    writer.writer.flush()?;
+    Ok(writer.packets)
+}
+
+/// Writes a pcapng file with a single section in little endian.
+struct PcapNgWriter<W: Write> {
+    writer: W,
+    /// Interfaces written so far by their capture and index in it.
+    interfaces: HashMap<(PathBuf, usize), u32>,
+    packets: usize,
+}
+
+impl<W: Write> PcapNgWriter<W> {
+    fn new(mut writer: W) -> io::Result<Self> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        // version 1.0
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        // section length not specified
+        body.extend_from_slice(&(-1i64).to_le_bytes());
+        let application = format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        push_option(&mut body, SHB_USER_APPL, application.as_bytes());
+        push_option(&mut body, OPT_END_OF_OPT, &[]);
+        write_block(&mut writer, SECTION_HEADER_BLOCK, &body)?;
+        Ok(PcapNgWriter { writer, interfaces: HashMap::new(), packets: 0 })
+    }
+
+    fn write_packet(&mut self, path: &Path, frame: &Frame, names: &[&str]) -> io::Result<()> {
+        let key = (path.to_path_buf(), frame.interface);
+        let interface = match self.interfaces.get(&key) {
+            Some(&interface) => interface,
+            None => {
+                let mut body = Vec::new();
+                body.extend_from_slice(&(frame.linktype.0 as u16).to_le_bytes());
+                // reserved
+                body.extend_from_slice(&0u16.to_le_bytes());
+                // no snapshot length, timestamps are in the default microseconds
+                body.extend_from_slice(&0u32.to_le_bytes());
+                write_block(&mut self.writer, INTERFACE_DESCRIPTION_BLOCK, &body)?;
+                let interface = self.interfaces.len() as u32;
+                self.interfaces.insert(key, interface);
+                interface
+            }
+        };
+
+        let timestamp = frame.timestamp.map_or(0, |(seconds, micros)| {
+            (seconds.max(0) as u64).saturating_mul(1_000_000).saturating_add(micros as u64)
+        });
+        let mut body = Vec::with_capacity(frame.data.len() + 64);
+        body.extend_from_slice(&interface.to_le_bytes());
+        body.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(timestamp as u32).to_le_bytes());
+        body.extend_from_slice(&(frame.data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&frame.original_len.to_le_bytes());
+        body.extend_from_slice(frame.data);
+        pad(&mut body);
+        for name in names {
+            push_option(&mut body, OPT_COMMENT, name.as_bytes());
+        }
+        if !names.is_empty() {
+            push_option(&mut body, OPT_END_OF_OPT, &[]);
+        }
+        write_block(&mut self.writer, ENHANCED_PACKET_BLOCK, &body)?;
+        self.packets += 1;
+        Ok(())
+    }
+}
+
+fn write_block(writer: &mut impl Write, block_type: u32, body: &[u8]) -> io::Result<()> {
+    // type and both lengths
+    let length = (body.len() + 12) as u32;
+    writer.write_all(&block_type.to_le_bytes())?;
+    writer.write_all(&length.to_le_bytes())?;
+    writer.write_all(body)?;
+    writer.write_all(&length.to_le_bytes())
+}
+
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    body.extend_from_slice(value);
+    pad(body);
+}
+
+/// Pads `body` to a multiple of 4 bytes.
+fn pad(body: &mut Vec<u8>) {
+    body.resize(body.len().next_multiple_of(4), 0);
+}
@@ -1,63 +1,141 @@
 use std::collections::HashSet;
-use crate::regex_pd::{PatternData, TAGS};
+use std::net::IpAddr;
+use std::str::FromStr;
+use crate::identifier::{Match, Packet, Protocol};
+use crate::regex_pd::{tags, CustomPatterns, PatternData};
 
+/// Decides which patterns take part in identification.
+///
+/// Start from `Filter::default()` and chain the setters:
+/// `Filter::default().rarity("0.2:1")?.exclude("url")?`.
+/// Included and excluded tags are checked once the [`Identifier`](crate::Identifier)
+/// using the filter identifies something, when the tags of its [`CustomPatterns`] are known.
+#[derive(Debug, Clone)]
 pub struct Filter {
     pub(crate) min: f32,
     pub(crate) max: f32,
     pub(crate) borderless: bool,
     pub(crate) include: HashSet<String>,
     pub(crate) exclude: HashSet<String>,
+    pub(crate) validated_only: bool,
+    pub(crate) protocols: HashSet<Protocol>,
+    pub(crate) ports: HashSet<u16>,
+    pub(crate) hosts: HashSet<IpAddr>,
 }
 
 impl Filter {
-    pub fn rarity(mut self, rarity: &String) -> Self {
-        let r = parse_rarity(rarity.as_str()).unwrap();
+    /// Only use patterns with a rarity in `min:max`, e.g. `0.1:1`.
+    pub fn rarity(mut self, rarity: &str) -> anyhow::Result<Self> {
+        let r = parse_rarity(rarity)?;
         self.min = r.0;
         self.max = r.1;
-        self
+        Ok(self)
     }
 
+    /// Match patterns anywhere inside the input instead of only the whole input.
     pub fn borderless(mut self, borderless: bool) -> Self {
         self.borderless = borderless;
         self
     }
 
-    pub fn include(mut self, include: &String) -> Self {
+    /// Only use patterns with at least one of these comma separated tags.
+    pub fn include(mut self, include: &str) -> anyhow::Result<Self> {
         if !include.is_empty() {
             self.include = include
                 .split(",")
                 .map(|s| s.to_string().to_lowercase())
                 .collect();
-            ensure_tags_exist(&self.include);
         }
-        self
+        Ok(self)
     }
 
-    pub fn exclude(mut self, exclude: &String) -> Self {
+    /// Skip patterns with any of these comma separated tags.
+    pub fn exclude(mut self, exclude: &str) -> anyhow::Result<Self> {
         if !exclude.is_empty() {
             self.exclude = exclude
                 .split(",")
                 .map(|s| s.to_string().to_lowercase())
                 .collect();
-            ensure_tags_exist(&self.exclude);
         }
+        Ok(self)
+    }
+
+    /// Only report matches whose checksum is valid, skipping patterns without a checksum.
+    /// Matches written without their checksum, like all lowercase Ethereum addresses, are kept.
+    pub fn validated_only(mut self, validated_only: bool) -> Self {
+        self.validated_only = validated_only;
         self
     }
 
+    /// Only scan packets of captures using one of these comma separated protocols, e.g. `tcp,udp`.
+    pub fn protocols(mut self, protocols: &str) -> anyhow::Result<Self> {
+        self.protocols = parse_list(protocols)?;
+        Ok(self)
+    }
+
+    /// Only scan packets of captures from or to one of these comma separated ports.
+    pub fn ports(mut self, ports: &str) -> anyhow::Result<Self> {
+        self.ports = parse_list(ports)?;
+        Ok(self)
+    }
+
+    /// Only scan packets of captures from or to one of these comma separated IP addresses.
+    pub fn hosts(mut self, hosts: &str) -> anyhow::Result<Self> {
+        self.hosts = parse_list(hosts)?;
+        Ok(self)
+    }
+
+    /// The filter for patterns whose matches get their rarity raised by `boost`, up to 1.
+    pub(crate) fn boosted(&self, boost: f32) -> Filter {
+        let mut filter = self.clone();
+        filter.min -= boost;
+        if filter.max < 1.0 {
+            filter.max -= boost;
+        }
+        filter
+    }
+
     pub fn gets_excluded(&self, pattern_data: &PatternData) -> bool {
         if pattern_data.rarity < self.min || pattern_data.rarity > self.max {
             return true
         }
+        if self.validated_only && pattern_data.validator.is_none() {
+            return true;
+        }
         if pattern_data.tags.iter()
-            .any(|&t| self.exclude.contains(&t.to_lowercase())) {
+            .any(|t| self.exclude.contains(&t.to_lowercase())) {
             return true;
         }
         if !self.include.is_empty() && !pattern_data.tags.iter()
-            .any(|&t| self.include.contains(&t.to_lowercase())) {
+            .any(|t| self.include.contains(&t.to_lowercase())) {
             return true
         }
         false
     }
+
+    /// Fails if an included or excluded tag isn't one of `known_tags`.
+    pub(crate) fn ensure_tags_exist(&self, known_tags: &HashSet<String>) -> anyhow::Result<()> {
+        ensure_tags_exist(&self.include, known_tags)?;
+        ensure_tags_exist(&self.exclude, known_tags)
+    }
+
+    /// Whether `packet` fails the protocol, port or host filters.
+    /// Packets that couldn't be dissected fail any of them.
+    pub fn gets_excluded_packet(&self, packet: &Packet) -> bool {
+        if !self.protocols.is_empty()
+            && !packet.protocol.is_some_and(|p| self.protocols.contains(&p)) {
+            return true;
+        }
+        if !self.ports.is_empty() && ![packet.src_port, packet.dst_port].iter()
+            .any(|port| port.is_some_and(|p| self.ports.contains(&p))) {
+            return true;
+        }
+        if !self.hosts.is_empty() && ![packet.src, packet.dst].iter()
+            .any(|host| host.is_some_and(|h| self.hosts.contains(&h))) {
+            return true;
+        }
+        false
+    }
 }
 
 impl Default for Filter {
@@ -68,22 +146,92 @@ impl Default for Filter {
             borderless: true,
             include: HashSet::new(),
             exclude: HashSet::new(),
+            validated_only: false,
+            protocols: HashSet::new(),
+            ports: HashSet::new(),
+            hosts: HashSet::new(),
         }
     }
 }
 
-fn ensure_tags_exist(tags: &HashSet<String>) {
-    if !tags.is_subset(&TAGS) {
-        let invalid_tags = tags.iter()
-            .filter(|&t| !TAGS.contains(t))
-            .collect::<Vec<&String>>();
-        if !invalid_tags.is_empty() {
-            eprintln!("Invalid tags: {:?}", invalid_tags);
-            std::process::exit(1);
+/// A condition a match has to meet to count as a failure, e.g. in CI.
+///
+/// Parsed from `rarity:<min>` (or just `<min>`) and `tag:<tag>`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailOn {
+    /// The match has at least this rarity.
+    Rarity(f32),
+    /// The match has this tag, compared case-insensitively.
+    Tag(String),
+}
+
+impl FailOn {
+    /// Parses `condition` like [`FromStr`], also accepting the tags of `patterns`.
+    pub fn parse(condition: &str, patterns: &CustomPatterns) -> anyhow::Result<Self> {
+        let (kind, value) = condition.split_once(':').unwrap_or(("rarity", condition));
+        match kind.to_lowercase().as_str() {
+            "rarity" => {
+                let min = value.parse::<f32>()?;
+                if !(0f32..=1f32).contains(&min) {
+                    anyhow::bail!("Invalid rarity {}. Rarity must be between 0 and 1 inclusive.", min);
+                }
+                Ok(FailOn::Rarity(min))
+            }
+            "tag" => {
+                let tag = HashSet::from([value.to_lowercase()]);
+                let mut known_tags = tags();
+                known_tags.extend(patterns.tags());
+                ensure_tags_exist(&tag, &known_tags)?;
+                Ok(FailOn::Tag(value.to_lowercase()))
+            }
+            _ => anyhow::bail!("Invalid fail condition {:?}. \
+            Format must be 'rarity:<min>' or 'tag:<tag>'.", condition),
+        }
+    }
+
+    pub fn is_met_by(&self, m: &Match) -> bool {
+        match self {
+            FailOn::Rarity(min) => m.rarity >= *min,
+            FailOn::Tag(tag) => m.tags.iter().any(|t| t.to_lowercase() == *tag),
         }
     }
 }
 
+impl FromStr for FailOn {
+    type Err = anyhow::Error;
+
+    fn from_str(condition: &str) -> anyhow::Result<Self> {
+        FailOn::parse(condition, &CustomPatterns::default())
+    }
+}
+
+/// Parses the comma separated values of `list`, an empty list gives an empty set.
+fn parse_list<T>(list: &str) -> anyhow::Result<HashSet<T>>
+where
+    T: FromStr + Eq + std::hash::Hash,
+    T::Err: std::fmt::Display,
+{
+    list.split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse::<T>().map_err(|e| anyhow::anyhow!("Invalid value {:?}: {}", value, e)))
+        .collect()
+}
+
+fn ensure_tags_exist(
+    include_or_exclude: &HashSet<String>,
+    known_tags: &HashSet<String>
+) -> anyhow::Result<()> {
+    let mut invalid_tags = include_or_exclude.iter()
+        .filter(|&t| !known_tags.contains(t))
+        .collect::<Vec<&String>>();
+    if !invalid_tags.is_empty() {
+        invalid_tags.sort();
+        anyhow::bail!("Invalid tags: {:?}", invalid_tags);
+    }
+    Ok(())
+}
+
 pub fn parse_rarity(rarity: &str) -> anyhow::Result<(f32, f32)> {
     let parts: Vec<&str> = rarity.split(':').collect();
     if parts.len() != 2 {
@@ -96,4 +244,4 @@ pub fn parse_rarity(rarity: &str) -> anyhow::Result<(f32, f32)> {
         anyhow::bail!("Invalid rarity range. Range must be between 0 and 1 inclusive.");
     }
     Ok((min, max))
-}
\ No newline at end of file
+}
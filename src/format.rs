@@ -1,8 +1,11 @@
+mod sarif;
+
 use std::cmp::min;
+use crate::format::sarif::to_sarif;
 use crate::identifier::Match;
+use crate::regex_pd::PatternData;
 use colored::*;
 use fancy_regex::Regex;
-use std::process;
 use tabled::settings::object::Columns;
 use tabled::settings::peaker::PriorityMax;
 use tabled::settings::Width;
@@ -12,12 +15,14 @@ use terminal_size::{terminal_size, Height as TerminalHeight, Width as TerminalWi
 use crate::options::Options;
 
 const MAX_TABLE_WIDTH: usize = 300;
+// used when not writing to a terminal, e.g. when piping into a file
+const FALLBACK_TERMINAL_SIZE: (usize, usize) = (160, 50);
 
 fn get_terminal_size() -> (usize, usize) {
-    let (TerminalWidth(width), TerminalHeight(height)) =
-        terminal_size().expect("failed to obtain a terminal size");
-
-    (width as usize, height as usize)
+    match terminal_size() {
+        Some((TerminalWidth(width), TerminalHeight(height))) => (width as usize, height as usize),
+        None => FALLBACK_TERMINAL_SIZE,
+    }
 }
 
 #[derive(Tabled)]
@@ -26,46 +31,57 @@ struct MatchTableRow {
     matched_on: String,
     #[tabled(rename = "Identified as")]
     name: String,
+    #[tabled(rename = "Location")]
+    location: String,
     #[tabled(rename = "Description")]
     description: String,
 }
 
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, Default)]
 pub enum OutputFormat {
+    #[default]
     DEFAULT,
     JSON,
     PRETTY,
-    RAW // TODO: implement
+    /// One uncolored `source:line:column:name:matched` record per match.
+    RAW,
+    /// SARIF 2.1.0 log for code-scanning tools.
+    SARIF,
 }
 
-pub fn output(matches: &Vec<Match>, options: &Options) {
+/// Prints `matches` in the format of `options`.
+/// `patterns` are those the matches were looked for with, SARIF logs list them as rules.
+pub fn output(matches: &[Match], options: &Options, patterns: &[PatternData]) {
     match options.format {
-        OutputFormat::DEFAULT => { c_print_default(&matches) },
-        OutputFormat::JSON => { c_print_json(&matches) },
-        OutputFormat::PRETTY => { c_print_pretty(&matches) },
-        OutputFormat::RAW => { c_print_raw(&matches) },
+        OutputFormat::DEFAULT => { c_print_default(matches) },
+        OutputFormat::JSON => { c_print_json(matches) },
+        OutputFormat::PRETTY => { c_print_pretty(matches) },
+        OutputFormat::RAW => { c_print_raw(matches, options) },
+        OutputFormat::SARIF => { c_print_sarif(matches, patterns) },
     }
 }
 
-pub fn get_format(format: &Option<&String>) -> OutputFormat {
+pub fn get_format(format: Option<&str>) -> anyhow::Result<OutputFormat> {
     match format {
         Some(format) => {
             let f: String = format.to_uppercase();
             match f.as_str() {
-                "JSON" => { OutputFormat::JSON },
-                "PRETTY" => { OutputFormat::PRETTY },
-                "DEFAULT" => { OutputFormat::DEFAULT },
-                "RAW" => { OutputFormat::RAW },
+                "JSON" => { Ok(OutputFormat::JSON) },
+                "PRETTY" => { Ok(OutputFormat::PRETTY) },
+                "DEFAULT" => { Ok(OutputFormat::DEFAULT) },
+                "RAW" => { Ok(OutputFormat::RAW) },
+                "SARIF" => { Ok(OutputFormat::SARIF) },
                 &_ => {
-                    eprintln!("Invalid format {}", f);
-                    process::exit(1);
+                    anyhow::bail!("Invalid format {}", f);
                 }
             }
         }
-        None => { OutputFormat::DEFAULT }
+        None => { Ok(OutputFormat::DEFAULT) }
     }
 }
 
-fn c_print_default(matches: &Vec<Match>) {
+fn c_print_default(matches: &[Match]) {
     let mut output: Vec<String> = Vec::new();
     for m in matches {
         output.push(String::from("\n"));
@@ -74,6 +90,44 @@ fn c_print_default(matches: &Vec<Match>) {
         output.push(String::from("\n"));
         output.push(String::from("Name: ").blue().to_string());
         output.push(m.name.clone());
+        output.push(String::from("\n"));
+        output.push(String::from("Location: ").blue().to_string());
+        output.push(format!("{} (bytes {}..{})", m.location, m.location.start, m.location.end));
+        if let Some(packet) = &m.packet {
+            output.push(String::from("\n"));
+            output.push(String::from("Packet: ").blue().to_string());
+            output.push(packet.to_string());
+        }
+        if let Some(field) = &m.field {
+            output.push(String::from("\n"));
+            output.push(String::from("Field: ").blue().to_string());
+            output.push(field.clone());
+        }
+        if let Some(key_path) = &m.key_path {
+            output.push(String::from("\n"));
+            output.push(String::from("Key path: ").blue().to_string());
+            output.push(key_path.clone());
+        }
+        if let Some(validated) = m.validated {
+            output.push(String::from("\n"));
+            output.push(String::from("Checksum: ").blue().to_string());
+            output.push(String::from(if validated { "valid" } else { "invalid" }));
+        }
+        if !m.decoded.is_empty() {
+            output.push(String::from("\n"));
+            output.push(String::from("Decoded from: ").blue().to_string());
+            output.push(decoding_chain(m));
+        }
+        if !m.hashes.is_empty() {
+            output.push(String::from("\n"));
+            output.push(String::from("Hash types: ").blue().to_string());
+            output.push(hash_types(m));
+        }
+        if let Some(details) = &m.details {
+            output.push(String::from("\n"));
+            output.push(String::from("Details: ").blue().to_string());
+            output.push(details.to_string());
+        }
         if let Some(description) = &m.description {
             output.push(String::from("\n"));
             output.push(String::from("Description: ").blue().to_string());
@@ -96,28 +150,71 @@ fn c_print_default(matches: &Vec<Match>) {
     println!("{}", parse_wikitext(output_text.as_str()));
 }
 
-fn c_print_json(matches: &Vec<Match>) {
+fn c_print_json(matches: &[Match]) {
     let json_output = serde_json::to_string(matches).unwrap();
     println!("{}", json_output);
 }
 
-fn c_print_pretty(matches: &Vec<Match>) {
+fn c_print_sarif(matches: &[Match], patterns: &[PatternData]) {
+    let sarif_output = serde_json::to_string_pretty(&to_sarif(matches, patterns)).unwrap();
+    println!("{}", sarif_output);
+}
+
+fn c_print_pretty(matches: &[Match]) {
     let rows = matches_to_table_rows(matches);
     let mut table = Table::new(rows);
     let (width, _height) = get_terminal_size();
     table
         .with(Style::modern())
         .with(Colorization::exact([Color::FG_MAGENTA], Rows::first()))
-        .modify(Columns::new(3..), Width::wrap(width - 65).keep_words(true))
-        .modify(Columns::new(0..2), Width::wrap(25).keep_words(true))
+        .modify(Columns::new(3..), Width::wrap(width.saturating_sub(90)).keep_words(true))
+        .modify(Columns::new(0..3), Width::wrap(25).keep_words(true))
         .with(Width::wrap(width).priority(PriorityMax))
         .with(Width::increase(min(width, MAX_TABLE_WIDTH)))
     ;
     println!("{}", table.to_string().as_str());
 }
 
-fn c_print_raw(_matches: &Vec<Match>) {
-    panic!("c_print_raw: not implemented yet");
+fn c_print_raw(matches: &[Match], options: &Options) {
+    let terminator = if options.null_terminated { "\0" } else { "\n" };
+    let mut output = String::new();
+    for m in matches {
+        let escape = |text: String| if options.null_terminated {
+            text
+        } else {
+            // keep one match per line
+            text.replace('\r', "\\r").replace('\n', "\\n")
+        };
+        // empty without details, so every record has the same fields
+        let details = m.details.as_ref().map(ToString::to_string).unwrap_or_default();
+        let fields = [
+            m.location.source.to_string(),
+            m.location.line.to_string(),
+            m.location.column.to_string(),
+            m.name.clone(),
+            escape(m.matched_on.clone()),
+            escape(details),
+        ];
+        output.push_str(&fields.join(&options.separator));
+        output.push_str(terminator);
+    }
+    print!("{}", output);
+}
+
+/// Encodings of a decoded match, e.g. `base64 -> gzip`.
+fn decoding_chain(m: &Match) -> String {
+    m.decoded.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<String>>()
+        .join(" -> ")
+}
+
+/// Likely algorithms of a hash with their cracking modes, most likely first.
+fn hash_types(m: &Match) -> String {
+    m.hashes.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<String>>()
+        .join(", ")
 }
 
 fn parse_wikitext(wikitext: &str) -> String {
@@ -164,27 +261,38 @@ fn parse_wikitext(wikitext: &str) -> String {
     result
 }
 
-fn matches_to_table_rows(matches: &Vec<Match>) -> Vec<MatchTableRow> {
+fn matches_to_table_rows(matches: &[Match]) -> Vec<MatchTableRow> {
     let mut result = Vec::new();
 
     for m in matches {
 
         let matched_on = m.matched_on.clone();
-        let name = m.name.clone();
+        let name = if m.decoded.is_empty() {
+            m.name.clone()
+        } else {
+            format!("{} -> {}", decoding_chain(m), m.name)
+        };
         let description;
         if let Some(m_desc) = &m.description {
-            description = parse_wikitext(&*m_desc.to_string())
+            description = parse_wikitext(m_desc)
         } else if let Some(link) = &m.link {
             let mut desc = String::from("Click here to analyse in the browser\n");
             desc += link.clone().as_str();
-            desc += &*m.matched_on.as_str();
-            description = parse_wikitext(&*desc);
+            desc += m.matched_on.as_str();
+            description = parse_wikitext(&desc);
+        } else if !m.hashes.is_empty() {
+            description = format!("Hash types: {}", hash_types(m));
         } else {
             description = String::from("None");
         }
+        let description = match &m.details {
+            Some(details) => format!("{}\n{}", details, description),
+            None => description,
+        };
         let row: MatchTableRow = MatchTableRow {
             matched_on,
             name,
+            location: m.location.to_string(),
             description,
         };
         result.push(row);
@@ -0,0 +1,210 @@
+use crate::identifier::{Match, Source};
+use crate::regex_pd::PatternData;
+use fancy_regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const FINGERPRINT_KEY: &str = "whatRs/v1";
+
+/// SARIF 2.1.0 log with one rule per pattern and one result per match.
+pub(crate) fn to_sarif(matches: &[Match], patterns: &[PatternData]) -> Value {
+    let rule_indices: HashMap<&str, usize> = patterns.iter()
+        .enumerate()
+        .map(|(i, p)| (&*p.name, i))
+        .collect();
+
+    let rules: Vec<Value> = patterns.iter().map(rule).collect();
+    let results: Vec<Value> = matches.iter()
+        .map(|m| result(m, rule_indices.get(m.name.as_str()).copied()))
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_HOMEPAGE"),
+                    "rules": rules,
+                }
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }]
+    })
+}
+
+fn rule(pattern: &PatternData) -> Value {
+    let description = pattern.description.as_deref().unwrap_or(&pattern.name);
+    let mut rule = json!({
+        "id": rule_id(&pattern.name),
+        "name": pattern.name,
+        "shortDescription": { "text": pattern.name },
+        "fullDescription": {
+            "text": wikitext_to_text(description),
+            "markdown": wikitext_to_markdown(description),
+        },
+        "defaultConfiguration": { "level": level(pattern.rarity) },
+        "properties": {
+            "tags": pattern.tags,
+            "rarity": pattern.rarity,
+        },
+    });
+    if let Some(url) = &pattern.url {
+        rule["helpUri"] = json!(url);
+    }
+    rule
+}
+
+fn result(m: &Match, rule_index: Option<usize>) -> Value {
+    let mut result = json!({
+        "ruleId": rule_id(&m.name),
+        "level": level(m.rarity),
+        "message": { "text": format!("Identified {:?} as {}", m.matched_on, m.name) },
+        "partialFingerprints": { FINGERPRINT_KEY: fingerprint(m) },
+    });
+    if let Some(rule_index) = rule_index {
+        result["ruleIndex"] = json!(rule_index);
+    }
+    if let Some(validated) = m.validated {
+        result["properties"]["validated"] = json!(validated);
+    }
+    if !m.decoded.is_empty() {
+        result["properties"]["decoded"] = json!(m.decoded);
+    }
+    if !m.hashes.is_empty() {
+        result["properties"]["hashes"] = json!(m.hashes);
+    }
+    if let Some(details) = &m.details {
+        result["properties"]["details"] = json!(details);
+    }
+    if let Some(packet) = &m.packet {
+        result["properties"]["packet"] = json!(packet);
+    }
+    if let Some(field) = &m.field {
+        result["properties"]["field"] = json!(field);
+    }
+    if let Some(key_path) = &m.key_path {
+        result["properties"]["keyPath"] = json!(key_path);
+    }
+
+    let location = &m.location;
+    match &location.source {
+        Source::Text | Source::Stdin => {}
+        Source::File { path } => {
+            result["locations"] = json!([{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": to_uri(path) },
+                    "region": {
+                        "startLine": location.line,
+                        "startColumn": location.column,
+                        "byteOffset": location.start,
+                        "byteLength": location.end - location.start,
+                        "snippet": { "text": m.matched_on },
+                    },
+                }
+            }]);
+        }
+        Source::Packet { path, index } => {
+            result["locations"] = json!([{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": to_uri(path) },
+                },
+                "properties": { "packet": index },
+            }]);
+        }
+        Source::Archive { path, entry } => {
+            result["locations"] = json!([{
+                "physicalLocation": {
+                    "artifactLocation": { "uri": to_uri(path) },
+                },
+                "properties": { "entry": entry, "line": location.line, "column": location.column },
+            }]);
+        }
+    }
+    result
+}
+
+/// Rarity is how specific a pattern is, so the rarer a pattern the more certain its matches.
+fn level(rarity: f32) -> &'static str {
+    if rarity >= 0.7 {
+        "error"
+    } else if rarity >= 0.3 {
+        "warning"
+    } else {
+        "note"
+    }
+}
+
+fn rule_id(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join("-")
+}
+
+/// Stays the same across runs as long as the same text is found in the same artifact,
+/// even if it moved within the artifact.
+fn fingerprint(m: &Match) -> String {
+    let artifact = match &m.location.source {
+        Source::Text | Source::Stdin => String::new(),
+        Source::File { path } | Source::Packet { path, .. } => to_uri(path),
+        Source::Archive { path, entry } => format!("{}!/{}", to_uri(path), entry),
+    };
+    format!("{:016x}", fnv1a(&[&rule_id(&m.name), &artifact, &m.matched_on]))
+}
+
+// 64-bit FNV-1a, unlike `DefaultHasher` its output is guaranteed to never change
+fn fnv1a(parts: &[&str]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for &byte in part.as_bytes().iter().chain(&[0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+fn to_uri(path: &Path) -> String {
+    let absolute = path.is_absolute();
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::with_capacity(path.len());
+    if absolute {
+        // Windows paths start with the drive letter instead of a slash
+        uri.push_str(if path.starts_with('/') { "file://" } else { "file:///" });
+    }
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/:".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+fn wikitext_to_text(wikitext: &str) -> String {
+    let text = strip_colors(wikitext);
+    Regex::new(r"\[link=(.*?)\](.*?)\[/link\]").unwrap()
+        .replace_all(&text, "$2 ($1)")
+        .to_string()
+}
+
+fn wikitext_to_markdown(wikitext: &str) -> String {
+    let text = strip_colors(wikitext);
+    Regex::new(r"\[link=(.*?)\](.*?)\[/link\]").unwrap()
+        .replace_all(&text, "[$2]($1)")
+        .to_string()
+}
+
+fn strip_colors(wikitext: &str) -> String {
+    Regex::new(r"\[#([A-Fa-f0-9]{6})\](.*?)\[/#\1\]").unwrap()
+        .replace_all(wikitext, "$2")
+        .to_string()
+}
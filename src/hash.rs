@@ -0,0 +1,147 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::fmt;
+
+/// Tag of the patterns whose matches are hashes, their matches list [`HashCandidate`]s.
+pub const HASH_TAG: &str = "Hash";
+
+/// An algorithm a hash may have been made with, along with the modes cracking tools use for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct HashCandidate {
+    pub name: &'static str,
+    /// Hash mode of hashcat, `-m <mode>`.
+    pub hashcat_mode: Option<u32>,
+    /// Format of John the Ripper (jumbo), `--format=<format>`.
+    pub john_format: Option<&'static str>,
+}
+
+impl fmt::Display for HashCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let modes: Vec<String> = [
+            self.hashcat_mode.map(|mode| format!("hashcat -m {}", mode)),
+            self.john_format.map(|format| format!("john --format={}", format)),
+        ].into_iter().flatten().collect();
+
+        if modes.is_empty() {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{} ({})", self.name, modes.join(", "))
+        }
+    }
+}
+
+const fn candidate(
+    name: &'static str,
+    hashcat_mode: Option<u32>,
+    john_format: Option<&'static str>
+) -> HashCandidate {
+    HashCandidate { name, hashcat_mode, john_format }
+}
+
+// Raw digests by number of hex characters, most common algorithm first
+const HEX_8: &[HashCandidate] = &[
+    candidate("CRC32", Some(11500), Some("crc32")),
+    candidate("Adler-32", None, None),
+];
+const HEX_16: &[HashCandidate] = &[
+    candidate("MySQL323", Some(200), Some("mysql")),
+    candidate("Half MD5", Some(5100), None),
+];
+const HEX_32: &[HashCandidate] = &[
+    candidate("MD5", Some(0), Some("raw-md5")),
+    candidate("NTLM", Some(1000), Some("nt")),
+    candidate("MD4", Some(900), Some("raw-md4")),
+    candidate("LM", Some(3000), Some("lm")),
+];
+// Windows tools write NTLM and LM hashes in uppercase
+const HEX_32_UPPERCASE: &[HashCandidate] = &[
+    candidate("NTLM", Some(1000), Some("nt")),
+    candidate("LM", Some(3000), Some("lm")),
+    candidate("MD5", Some(0), Some("raw-md5")),
+    candidate("MD4", Some(900), Some("raw-md4")),
+];
+const HEX_40: &[HashCandidate] = &[
+    candidate("SHA-1", Some(100), Some("raw-sha1")),
+    candidate("RIPEMD-160", Some(6000), Some("ripemd-160")),
+];
+const HEX_56: &[HashCandidate] = &[
+    candidate("SHA-224", Some(1300), Some("raw-sha224")),
+    candidate("SHA3-224", Some(17300), None),
+    candidate("Keccak-224", Some(17700), None),
+];
+const HEX_64: &[HashCandidate] = &[
+    candidate("SHA-256", Some(1400), Some("raw-sha256")),
+    candidate("SHA3-256", Some(17400), None),
+    candidate("Keccak-256", Some(17800), Some("raw-keccak-256")),
+    candidate("BLAKE2s-256", None, None),
+    candidate("GOST R 34.11-94", Some(6900), Some("gost")),
+];
+const HEX_96: &[HashCandidate] = &[
+    candidate("SHA-384", Some(10800), Some("raw-sha384")),
+    candidate("SHA3-384", Some(17500), None),
+    candidate("Keccak-384", Some(17900), None),
+];
+const HEX_128: &[HashCandidate] = &[
+    candidate("SHA-512", Some(1700), Some("raw-sha512")),
+    candidate("SHA3-512", Some(17600), Some("raw-sha3")),
+    candidate("BLAKE2b-512", None, None),
+    candidate("Keccak-512", Some(18000), Some("raw-keccak")),
+    candidate("Whirlpool", Some(6100), Some("whirlpool")),
+];
+
+/// Formats that carry their algorithm, mostly modular crypt strings like `$6$salt$hash`.
+static FORMATS: Lazy<Vec<(Regex, HashCandidate)>> = Lazy::new(|| {
+    [
+        (r"^\$2[abxy]?\$[0-9]{2}\$[./A-Za-z0-9]{53}$", candidate("bcrypt", Some(3200), Some("bcrypt"))),
+        (r"^\$1\$[^$]{0,8}\$[./A-Za-z0-9]{22}$", candidate("md5crypt", Some(500), Some("md5crypt"))),
+        (r"^\$apr1\$[^$]{0,8}\$[./A-Za-z0-9]{22}$", candidate("Apache MD5 (apr1)", Some(1600), Some("md5crypt"))),
+        (r"^\$5\$(rounds=[0-9]+\$)?[^$]{0,16}\$[./A-Za-z0-9]{43}$", candidate("sha256crypt", Some(7400), Some("sha256crypt"))),
+        (r"^\$6\$(rounds=[0-9]+\$)?[^$]{0,16}\$[./A-Za-z0-9]{86}$", candidate("sha512crypt", Some(1800), Some("sha512crypt"))),
+        (r"^\$argon2(id|i|d)\$(v=[0-9]+\$)?m=[0-9]+,t=[0-9]+,p=[0-9]+\$[A-Za-z0-9+/]+\$[A-Za-z0-9+/]+$", candidate("Argon2", Some(34000), Some("argon2"))),
+        (r"^\$7\$[./A-Za-z0-9]{11,}\$[./A-Za-z0-9]{43}$", candidate("scrypt", None, Some("scrypt"))),
+        (r"^SCRYPT:[0-9]+:[0-9]+:[0-9]+:[A-Za-z0-9+/=]+:[A-Za-z0-9+/=]+$", candidate("scrypt", Some(8900), None)),
+        (r"^pbkdf2_sha256\$[0-9]+\$[^$]+\$[A-Za-z0-9+/=]+$", candidate("Django (PBKDF2-SHA256)", Some(10000), Some("django"))),
+        (r"^sha1\$[^$]*\$[a-f0-9]{40}$", candidate("Django (SHA-1)", Some(124), None)),
+        (r"^\$pbkdf2-sha256\$[0-9]+\$[./A-Za-z0-9]+\$[./A-Za-z0-9]+$", candidate("PBKDF2-HMAC-SHA256 (passlib)", Some(20300), Some("pbkdf2-hmac-sha256"))),
+        (r"^\$pbkdf2-sha512\$[0-9]+\$[./A-Za-z0-9]+\$[./A-Za-z0-9]+$", candidate("PBKDF2-HMAC-SHA512 (passlib)", Some(20200), Some("pbkdf2-hmac-sha512"))),
+        (r"^\$pbkdf2\$[0-9]+\$[./A-Za-z0-9]+\$[./A-Za-z0-9]+$", candidate("PBKDF2-HMAC-SHA1 (passlib)", Some(20400), Some("pbkdf2-hmac-sha1"))),
+        (r"^sha256:[0-9]+:[A-Za-z0-9+/=]+:[A-Za-z0-9+/=]+$", candidate("PBKDF2-HMAC-SHA256", Some(10900), None)),
+        (r"^\$[PH]\$[./A-Za-z0-9]{31}$", candidate("phpass (WordPress, phpBB3)", Some(400), Some("phpass"))),
+        (r"^\$S\$[./A-Za-z0-9]{52}$", candidate("Drupal 7", Some(7900), Some("drupal7"))),
+        (r"^\*[A-F0-9]{40}$", candidate("MySQL 4.1+", Some(300), Some("mysql-sha1"))),
+    ]
+        .into_iter()
+        .map(|(regex, candidate)| (Regex::new(regex).unwrap(), candidate))
+        .collect()
+});
+
+/// Algorithms `hash` may have been made with, most likely first.
+///
+/// Raw hex digests only tell their length, so they usually have several candidates.
+/// Formats like bcrypt's `$2b$...` name their algorithm and have a single one.
+/// Returns nothing if `hash` doesn't look like a hash.
+pub fn identify_hash(hash: &str) -> Vec<HashCandidate> {
+    let hash = hash.trim();
+    if let Some((_, candidate)) = FORMATS.iter().find(|(regex, _)| regex.is_match(hash)) {
+        return vec![*candidate];
+    }
+
+    if !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Vec::new();
+    }
+    let uppercase = hash.bytes().any(|b| b.is_ascii_uppercase());
+    let candidates = match hash.len() {
+        8 => HEX_8,
+        16 => HEX_16,
+        32 if uppercase => HEX_32_UPPERCASE,
+        32 => HEX_32,
+        40 => HEX_40,
+        56 => HEX_56,
+        64 => HEX_64,
+        96 => HEX_96,
+        128 => HEX_128,
+        _ => &[],
+    };
+    candidates.to_vec()
+}
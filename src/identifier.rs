@@ -1,19 +1,53 @@
-mod pcap;
+mod application;
+mod archive;
+mod decode;
+mod location;
+pub(crate) mod capture;
+pub(crate) mod packet;
+mod reassembly;
+pub(crate) mod signature;
+mod strings;
+mod structured;
 
 use std::collections::HashSet;
-use crate::regex_pd::{PATTERN_DATA, REGEX, REGEX_NO_ANCHOR};
+use crate::regex_pd::{
+    patterns, CustomPatterns, PatternData, PatternRegex, PATTERN_DATA, REGEX, REGEX_LINES,
+    REGEX_NO_ANCHOR
+};
+use crate::details::{details, Details};
+use crate::hash::{identify_hash, HashCandidate, HASH_TAG};
 use crate::Filter;
 use serde::Serialize;
-use std::fs;
+use std::fs::File;
+use std::io::Read;
 use std::path::Path;
-use std::sync::{Arc, Mutex, RwLock};
+use ignore::overrides::OverrideBuilder;
+use ignore::WalkBuilder;
 use once_cell::sync::Lazy;
 use rayon::prelude::*;
-use regex::Regex;
-use crate::identifier::pcap::identify_pcapng;
+use crate::identifier::decode::decode_all;
+use crate::identifier::location::ScanInput;
+use crate::identifier::archive::{identify_archive, is_archive};
+use crate::identifier::capture::{identify_capture, is_capture};
+use crate::identifier::signature::find_signatures;
+use crate::identifier::strings::find_strings;
+use crate::identifier::structured::{identify_structured, is_structured};
 use crate::options::Options;
 
-#[derive(Debug, Serialize)]
+pub use crate::identifier::decode::Encoding;
+pub use crate::identifier::location::{Location, Source};
+pub use crate::identifier::packet::{Packet, Protocol};
+
+// Files are identified in windows of this size
+const WINDOW_LEN: usize = 8 << 20;
+/// Longest match found across the seam between two windows of a file or chunks of a TCP stream.
+pub(crate) const MAX_MATCH_LEN: usize = 4 << 10;
+
+/// Texts that were already reported, used to drop duplicate matches.
+pub(crate) type MatchedTexts = HashSet<String>;
+
+/// A single piece of input identified as a known pattern.
+#[derive(Debug, Clone, Serialize)]
 pub struct Match {
     pub matched_on: String,
     pub name: String,
@@ -21,238 +55,472 @@ pub struct Match {
     pub description: Option<String>,
     pub link: Option<String>,
     pub exploit: Option<String>,
+    pub tags: Vec<String>,
+    pub location: Location,
+    /// Whether the checksum of the matched text is valid,
+    /// `None` if the pattern or the way the text is written has no checksum.
+    pub validated: Option<bool>,
+    /// Encodings the matched text was hidden under, outermost first.
+    /// Its location is then the one of the encoded text.
+    pub decoded: Vec<Encoding>,
+    /// Algorithms a matched hash may have been made with, most likely first.
+    pub hashes: Vec<HashCandidate>,
+    /// What the matched text decodes to, e.g. the claims of a JWT or the date of a timestamp.
+    pub details: Option<Details>,
+    /// Packet of a capture the match was found in, with its addresses, ports and protocol.
+    pub packet: Option<Packet>,
+    /// Field of an HTTP, DNS or TLS message the match was found in, e.g. `http.authorization`,
+    /// named like the fields of Wireshark's display filters.
+    pub field: Option<String>,
+    /// Key path of the value of a JSON, YAML, TOML, INI or dotenv file the match was found in,
+    /// e.g. `database.password` or `servers[0].host`.
+    pub key_path: Option<String>,
+}
+
+impl Match {
+    pub(crate) fn new(pattern: &PatternData, matched_on: String, location: Location) -> Self {
+        Match {
+            name: pattern.name.to_string(),
+            rarity: pattern.rarity,
+            description: pattern.description.as_deref().map(str::to_string),
+            link: pattern.url.as_deref().map(str::to_string),
+            exploit: pattern.exploit.as_deref().map(str::to_string),
+            tags: pattern.tags.iter().map(|t| t.to_string()).collect(),
+            location,
+            validated: None,
+            decoded: Vec::new(),
+            hashes: if pattern.tags.iter().any(|t| t == HASH_TAG) {
+                identify_hash(&matched_on)
+            } else {
+                Vec::new()
+            },
+            details: details(&pattern.tags, &matched_on),
+            packet: None,
+            field: None,
+            key_path: None,
+            matched_on,
+        }
+    }
 }
 
+/// Identifies known patterns in text, files, directories and packet captures.
+///
+/// Configured like [`Filter`]: start from `Identifier::default()` and chain the setters.
+#[derive(Debug, Clone, Default)]
 pub struct Identifier {
-    matched_texts: Arc<RwLock<HashSet<String>>>,
+    filter: Filter,
+    options: Options,
+    patterns: CustomPatterns,
 }
 
 impl Identifier {
+    /// Also identify the patterns of `patterns`, loaded at runtime.
+    pub fn patterns(mut self, patterns: CustomPatterns) -> Self {
+        self.patterns = patterns;
+        self
+    }
 
-    pub fn new() -> Identifier {
-        Identifier {
-            matched_texts: Arc::new(RwLock::new(HashSet::new())),
-        }
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
     }
 
-    pub fn identify_text(&mut self, text: String, matches: &mut Vec<Match>, filter: &Filter, options: &Options) {
-        let text = Arc::new(text);
-        let matches_arc = Arc::new(Mutex::new(Vec::new()));
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
 
-        PATTERN_DATA
-            .par_iter()
-            .enumerate()
-            .for_each(|(i, r)| {
-                if filter.gets_excluded(&r) {
-                    return;
-                }
+    /// All patterns used for identification, compiled-in and loaded at runtime.
+    pub fn pattern_data(&self) -> Vec<PatternData> {
+        let mut data = patterns();
+        data.extend(self.patterns.data().cloned());
+        data
+    }
 
-                let re: &Lazy<Regex> = if filter.borderless {
-                    &REGEX_NO_ANCHOR[i]
-                } else {
-                    &REGEX[i]
-                };
+    /// All tags used by the patterns, lowercased.
+    pub fn tags(&self) -> HashSet<String> {
+        let mut tags = crate::regex_pd::tags();
+        tags.extend(self.patterns.tags());
+        tags
+    }
 
-                // Find all matches for this pattern
-                for mat in re.find_iter(&text) {
+    /// Fails if the filter includes or excludes a tag of no pattern, checked on use
+    /// so it doesn't matter whether the filter or the custom patterns are set first.
+    fn ensure_tags_exist(&self) -> anyhow::Result<()> {
+        self.filter.ensure_tags_exist(&self.tags())
+    }
 
-                    let matched_on = mat.as_str().to_string();
+    /// Identifies `input` as a path if it points to a file or directory, otherwise as text.
+    pub fn identify(&self, input: &str, matches: &mut Vec<Match>) -> anyhow::Result<()> {
+        let path = Path::new(input);
+        if !self.options.only_text && path.exists() {
+            self.identify_path(path, matches)?;
+        } else {
+            self.identify_text(input, matches)?;
+        }
 
-                    if !options.allow_duplicates {
-                        if self.matched_texts.read().unwrap().contains(&matched_on) {
-                            continue
-                        } else {
-                            self.matched_texts.write().unwrap().insert(matched_on.clone());
-                        }
-                    }
+        Ok(())
+    }
 
-                    let match_obj = Match {
-                        matched_on,
-                        name: r.name.parse().unwrap(),
-                        rarity: r.rarity,
-                        description: match &r.description {
-                            Some(description) => Some(description.to_string()),
-                            None => None
-                        },
-                        link: match &r.url {
-                            Some(url) => Some(url.to_string()),
-                            None => None
-                        },
-                        exploit: match &r.exploit {
-                            Some(exploit) => Some(exploit.to_string()),
-                            None => None
-                        },
-                    };
-
-                    // Push the match object to the shared vector
-                    let mut matches_lock = matches_arc.lock().unwrap();
-                    matches_lock.push(match_obj);
-                }
-            });
+    /// Identifies the file or, recursively, the directory at `path`.
+    pub fn identify_path(&self, path: &Path, matches: &mut Vec<Match>) -> anyhow::Result<()> {
+        if path.is_file() {
+            self.identify_file(path, matches)
+        } else if path.is_dir() {
+            self.identify_directory(path, matches)
+        } else if path.exists() {
+            anyhow::bail!("Input {:?} is a path but neither file nor directory", path)
+        } else {
+            anyhow::bail!("No such file or directory: {:?}", path)
+        }
+    }
 
-        // Move collected matches from matches_arc to the output vector
-        let results = Arc::try_unwrap(matches_arc)
-            .expect("Failed to unwrap Arc") // We ensure no other thread is holding a reference
-            .into_inner()
-            .expect("Failed to lock Mutex");
+    pub fn identify_text(&self, text: &str, matches: &mut Vec<Match>) -> anyhow::Result<()> {
+        self.ensure_tags_exist()?;
+        self.identify_input(&ScanInput::from_text(text), &mut MatchedTexts::default(), matches);
+        Ok(())
+    }
 
-        matches.extend(results);
+    /// Identifies the content read from `reader` the same way as the content of a file,
+    /// window by window so it never has to fit in memory at once.
+    pub fn identify_reader<R: Read>(
+        &self,
+        source: Source,
+        reader: R,
+        matches: &mut Vec<Match>
+    ) -> anyhow::Result<()> {
+        self.ensure_tags_exist()?;
+        self.identify_windows(source, reader, matches)
     }
-}
 
-pub fn identify_directory(path: &Path, matches: &mut Vec<Match>, filter: &Filter, options: &Options) -> anyhow::Result<()> {
-    println!("Identifying directory: {:?}", path);
-    for entry in fs::read_dir(path)? {
-        let entry = entry?;
-        let file_path = entry.path();
-        if file_path.is_file() {
-            identify_file(&file_path, matches, filter, options)?;
-        } else if file_path.is_dir() {
-            identify_directory(&file_path, matches, filter, options)?;
+    /// Identifies every file below `path`, recursively.
+    ///
+    /// Files excluded by `.gitignore` and `.ignore` files, hidden files and files not passing
+    /// the glob filters of the [`Options`] are skipped.
+    /// So are files that can't be read or identified, e.g. corrupt captures or archives,
+    /// after printing why to stderr.
+    ///
+    /// Files are scanned in parallel on the current rayon thread pool, run this inside
+    /// [`rayon::ThreadPool::install`] to limit the number of threads.
+    /// Matches are still ordered by path.
+    pub fn identify_directory(&self, path: &Path, matches: &mut Vec<Match>) -> anyhow::Result<()> {
+        self.ensure_tags_exist()?;
+        if self.options.verbose {
+            println!("Identifying directory: {:?}", path);
+        }
+        let mut files = Vec::new();
+        for entry in self.walk_builder(path)?.build() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    eprintln!("Skipping {}", e);
+                    continue;
+                }
+            };
+            if entry.file_type().is_some_and(|t| t.is_file()) {
+                files.push(entry.into_path());
+            }
         }
+
+        // Every file gets its own vector, so threads never wait for each other
+        let file_matches = files
+            .par_iter()
+            .map(|file| {
+                let mut file_matches = Vec::new();
+                match self.identify_file(file, &mut file_matches) {
+                    Ok(()) => file_matches,
+                    Err(e) => {
+                        // like unreadable entries, an unreadable or corrupt file doesn't end the scan
+                        eprintln!("Skipping {}: {:#}", file.display(), e);
+                        Vec::new()
+                    }
+                }
+            })
+            .collect::<Vec<Vec<Match>>>();
+        matches.extend(file_matches.into_iter().flatten());
+        Ok(())
     }
-    Ok(())
-}
 
-pub fn identify_file(path: &Path, matches: &mut Vec<Match>, filter: &Filter, options: &Options) -> anyhow::Result<()> {
-    // TODO: Better error handling
-    println!("Identifying file {:?}", path);
+    fn walk_builder(&self, path: &Path) -> anyhow::Result<WalkBuilder> {
+        let options = &self.options;
+        let mut overrides = OverrideBuilder::new(path);
+        for glob in &options.globs {
+            overrides.add(glob)?;
+        }
+        for glob in &options.exclude_globs {
+            overrides.add(&format!("!{}", glob))?;
+        }
 
-    if options.pcapng {
-        identify_pcapng(path, matches, filter, options)?;
-    } else {
-        let content = read_file_to_strings(path).join("\n");
-        Identifier::new().identify_text(content, matches, filter, options);
+        let mut builder = WalkBuilder::new(path);
+        builder
+            .standard_filters(!options.no_ignore)
+            .hidden(!options.hidden)
+            .require_git(false)
+            .max_depth(options.max_depth)
+            .follow_links(options.follow_symlinks)
+            .max_filesize(options.max_filesize)
+            .overrides(overrides.build()?)
+            .sort_by_file_name(|a, b| a.cmp(b));
+        Ok(builder)
     }
 
-    Ok(())
-}
+    /// Identifies the format of a file by its signature and the text in it,
+    /// the packets of a pcap or pcapng capture, or the files in an archive.
+    ///
+    /// Files of UTF-8 text are identified as a whole, binary files by the printable strings
+    /// of at least [`Options::min_string_len`] characters in them. Strings in UTF-16 are found
+    /// as well, their matches have the encoding first in [`Match::decoded`].
+    ///
+    /// Captures are recognized by their magic number,
+    /// with [`Options::capture`] set files that aren't one are an error.
+    /// So are zip and tar archives and gzip, bzip2, xz and zstd compressed files, which are
+    /// looked into up to [`Options::archive_depth`] levels deep.
+    /// The values of JSON, YAML, TOML, INI and dotenv files are identified one by one,
+    /// with their key path in [`Match::key_path`].
+    pub fn identify_file(&self, path: &Path, matches: &mut Vec<Match>) -> anyhow::Result<()> {
+        self.ensure_tags_exist()?;
+        if self.options.verbose {
+            println!("Identifying file {:?}", path);
+        }
 
-pub fn identify(input: &String, matches: &mut Vec<Match>, filter: &Filter, options: &Options) -> anyhow::Result<()> {
-    let path = Path::new(input);
-    if !options.only_text && path.exists() {
-        if path.is_file() {
-            identify_file(path, matches, &filter, &options)?;
-        } else if path.is_dir() {
-            identify_directory(path, matches, &filter, &options)?;
+        if self.options.capture || is_capture(path)? {
+            identify_capture(self, path, matches)?;
+        } else if self.options.archive_depth > 0 && is_archive(path)? {
+            identify_archive(self, path, matches)?;
+        } else if is_structured(path)? {
+            identify_structured(self, path, matches)?;
         } else {
-            panic!("Input is path but neither file nor directory");
+            let source = Source::File { path: path.to_path_buf() };
+            self.identify_windows(source, File::open(path)?, matches)?;
         }
-    } else {
-        Identifier::new().identify_text(input.to_string(), matches, &filter, &options);
-    }
 
-    Ok(())
-}
-
-fn read_file_to_strings(filename: &Path) -> Vec<String> {
-    let file = fs::read(filename).expect("File not found");
-    to_human_readable_vec(file)
-}
-
-pub(crate) fn to_human_readable_vec(b_string: Vec<u8>) -> Vec<String> {
-    let mut printable_text: Vec<String> = Vec::new();
-    let min_human_text_len = 4;
-
-    // This struct is used to check if our chunk division divided a human-readable sequence
-    // If Texts[n].ends_with_valid_utf8 && Texts[n+1].starts_with_valid_utf8 -> stitch Texts together
-    struct Paragraph {
-        sentences: Vec<String>,
-        starts_with_valid_utf8: bool,
-        ends_with_valid_utf8: bool,
+        Ok(())
     }
 
-    let text = b_string
-        .par_chunks(1 << 16)
-        .map(|chunk| {
-            let mut use_current_buffer = false;
-            let mut buffer: Vec<u8> = Vec::new();
-            let mut paragraph: Paragraph = Paragraph {
-                sentences: vec![],
-                starts_with_valid_utf8: false,
-                ends_with_valid_utf8: false,
+    /// Identifies the content read from `reader` in windows of [`WINDOW_LEN`] bytes,
+    /// so memory use doesn't grow with the size of a file.
+    ///
+    /// Each window repeats the last [`MAX_MATCH_LEN`] bytes of the one before, and only keeps
+    /// the matches starting after them, so matches across a seam are found exactly once.
+    pub(crate) fn identify_windows(
+        &self,
+        source: Source,
+        mut reader: impl Read,
+        matches: &mut Vec<Match>
+    ) -> anyhow::Result<()> {
+        let mut matched_texts = MatchedTexts::default();
+        let mut window: Vec<u8> = Vec::with_capacity(WINDOW_LEN);
+        let (mut offset, mut lines_before, mut columns_before) = (0, 0, 0);
+        // bytes at the start of the window identified with the previous one already
+        let mut lead = 0;
+        let mut text = None;
+
+        loop {
+            (&mut reader).take((WINDOW_LEN - window.len()) as u64).read_to_end(&mut window)?;
+            let last = window.len() < WINDOW_LEN;
+            // matches starting later may go on past the window, the next one finds them
+            let end = if last { window.len() } else { window.len() - MAX_MATCH_LEN };
+
+            // decided by the first window
+            let text = *text.get_or_insert_with(|| is_text(&window, last));
+            // the text of binary files is split by the encoding of their strings
+            let inputs: Vec<(ScanInput, Option<Encoding>)> = if text {
+                vec![(ScanInput::from_lossy(source.clone(), &window).by_line(), None)]
+            } else {
+                let strings = find_strings(&window, self.options.min_string_len);
+                vec![
+                    (ScanInput::from_ranges(source.clone(), &window, &strings.utf8), None),
+                    (ScanInput::from_utf16(source.clone(), &window, &strings.utf16le, false),
+                        Some(Encoding::Utf16Le)),
+                    (ScanInput::from_utf16(source.clone(), &window, &strings.utf16be, true),
+                        Some(Encoding::Utf16Be)),
+                ]
+            };
+            let inputs: Vec<(ScanInput, Option<Encoding>)> = inputs.into_iter()
+                .map(|(input, encoding)| {
+                    (input.starting_at(offset, lines_before).continuing_line(columns_before), encoding)
+                })
+                .collect();
+            let owned = offset + lead..offset + end;
+            let in_window = |found: Vec<Match>| -> Vec<Match> {
+                found.into_iter().filter(|m| owned.contains(&m.location.start)).collect()
             };
-            paragraph.starts_with_valid_utf8 = chunk[0].is_ascii_graphic();
-            paragraph.ends_with_valid_utf8 = chunk[chunk.len() - 1].is_ascii_graphic();
-
-            for (i, &character) in chunk.iter().enumerate() {
-                if character.is_ascii_graphic() {
-                    // Doesn't consider whitespace as a graphic!
-                    use_current_buffer = true;
-                    buffer.push(character);
-                } else if use_current_buffer {
-                    // If the char isn't ascii graphic, that means this is the end for our string which we are interested in
-                    // string with length less than 4 most likely won't be of our use.
-                    // If it has length more than 4, then push it to our `printable_text`
-                    if buffer.len() >= min_human_text_len
-                        || i < min_human_text_len
-                        || i >= chunk.len() - min_human_text_len {
-                        paragraph.sentences.push(
-                            String::from_utf8(buffer.clone()).expect("failed to convert u8 to string"),
-                        );
+            // magic bytes in text are words that happen to match, like "BM" or "%PDF-"
+            if !text {
+                self.add_matches(in_window(find_signatures(self, &inputs[0].0, &window)),
+                    &mut matched_texts, matches);
+            }
+            for (input, encoding) in &inputs {
+                let mut found = in_window(self.find_all(input));
+                if let Some(encoding) = *encoding {
+                    for m in &mut found {
+                        m.decoded.insert(0, encoding);
                     }
-                    // Clear the buffer so that current contents of it won't affect the next string.
-                    buffer.clear();
-                    // We set this to false because we don't want to use buffer until we get an ascii graphic!
-                    use_current_buffer = false;
                 }
+                self.add_matches(found, &mut matched_texts, matches);
             }
-            if buffer.len() >= min_human_text_len {
-                paragraph.sentences.push(
-                    String::from_utf8(buffer).expect("failed to convert u8 to string")
-                );
+            if last {
+                break;
             }
-            paragraph
-        })
-        .collect::<Vec<Paragraph>>();
-
-    if !text.is_empty() && !text.first().unwrap().sentences.is_empty() {
-        let paragraph = text.first().unwrap();
-        if paragraph.sentences.first().unwrap().len() >= min_human_text_len {
-            printable_text.push(paragraph.sentences.first().unwrap().clone());
+
+            let kept_from = end - MAX_MATCH_LEN;
+            let dropped = &window[..kept_from];
+            lines_before += dropped.iter().filter(|&&b| b == b'\n').count();
+            let line_start = dropped.iter().rposition(|&b| b == b'\n').map(|n| n + 1);
+            // characters of the line the next window starts in, not counting UTF-8 continuation bytes
+            let columns = dropped[line_start.unwrap_or(0)..].iter()
+                .filter(|&&b| b & 0xc0 != 0x80)
+                .count();
+            columns_before = if line_start.is_some() { columns } else { columns_before + columns };
+            window.drain(..kept_from);
+            offset += kept_from;
+            lead = MAX_MATCH_LEN;
         }
-    }
 
+        Ok(())
+    }
 
-    for i in 0..text.len()-1 {
-        let paragraph = &text[i];
+    /// Identifies the text of `input`, skipping texts already in `matched_texts`
+    /// so duplicates are dropped across several calls.
+    ///
+    /// With [`Options::decode_depth`] set, text decoded from encoded parts of `input`
+    /// is identified as well.
+    pub(crate) fn identify_input(
+        &self,
+        input: &ScanInput,
+        matched_texts: &mut MatchedTexts,
+        matches: &mut Vec<Match>
+    ) {
+        self.add_matches(self.find_all(input), matched_texts, matches);
+    }
 
-        if paragraph.sentences.is_empty() {
-            continue;
+    /// Matches of the patterns in `input` and, with [`Options::decode_depth`] set,
+    /// in text decoded from it.
+    fn find_all(&self, input: &ScanInput) -> Vec<Match> {
+        let mut found = self.find_patterns(input);
+        if self.options.decode_depth > 0 {
+            found.extend(self.find_decoded(input));
         }
+        found
+    }
 
-        let paragraph_next = &text[i+1];
-        for j in 1..paragraph.sentences.len()-1 {
-            printable_text.push(paragraph.sentences[j].clone());
-        }
-        if paragraph.ends_with_valid_utf8 && paragraph_next.starts_with_valid_utf8 {
-            let mut s: String = paragraph.sentences.last().unwrap().clone();
-            if !paragraph_next.sentences.is_empty() {
-                s += paragraph_next.sentences.first().unwrap();
-            }
-            if s.len() >= min_human_text_len {
-                printable_text.push(s);
-            }
-        } else {
-            if paragraph.sentences.last().unwrap().len() >= min_human_text_len {
-                printable_text.push(paragraph.sentences.last().unwrap().clone());
-            }
-            if !paragraph_next.sentences.is_empty()
-                && paragraph_next.sentences.first().unwrap().len() >= min_human_text_len {
-                printable_text.push(paragraph_next.sentences.first().unwrap().clone());
+    /// Adds the matches of `found` whose text wasn't matched before, unless duplicates are allowed.
+    fn add_matches(
+        &self,
+        found: Vec<Match>,
+        matched_texts: &mut MatchedTexts,
+        matches: &mut Vec<Match>
+    ) {
+        for match_obj in found {
+            if self.options.allow_duplicates || matched_texts.insert(match_obj.matched_on.clone()) {
+                matches.push(match_obj);
             }
         }
     }
 
-    if !text.is_empty() && !text.last().unwrap().sentences.is_empty() {
-        let paragraph = text.last().unwrap();
-        for i in 1..paragraph.sentences.len()-1 {
-            printable_text.push(paragraph.sentences[i].clone());
-        }
-        if paragraph.sentences.last().unwrap().len() >= min_human_text_len {
-            printable_text.push(paragraph.sentences.last().unwrap().clone());
+    /// Matches of all patterns passing the filter, in pattern order.
+    fn find_patterns(&self, input: &ScanInput) -> Vec<Match> {
+        let filter = &self.filter;
+
+        let builtin_matches = PATTERN_DATA
+            .par_iter()
+            .enumerate()
+            .map(|(i, r)| {
+                if filter.gets_excluded(r) {
+                    return Vec::new();
+                }
+
+                let re: &Lazy<PatternRegex> = if filter.borderless {
+                    &REGEX_NO_ANCHOR[i]
+                } else if input.lines {
+                    &REGEX_LINES[i]
+                } else {
+                    &REGEX[i]
+                };
+
+                self.find_pattern(input, r, re)
+            });
+
+        let custom_matches = self.patterns
+            .as_slice()
+            .par_iter()
+            .map(|p| {
+                if filter.gets_excluded(&p.data) {
+                    return Vec::new();
+                }
+
+                let re: &PatternRegex = if filter.borderless {
+                    &p.regex_no_anchor
+                } else if input.lines {
+                    &p.regex_lines
+                } else {
+                    &p.regex
+                };
+
+                self.find_pattern(input, &p.data, re)
+            });
+
+        // Collected per pattern and merged in pattern order, so which pattern keeps a
+        // duplicate and the order of matches don't depend on thread scheduling
+        builtin_matches
+            .chain(custom_matches)
+            .collect::<Vec<Vec<Match>>>()
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Matches in text decoded from `input`, decoding up to [`Options::decode_depth`] times.
+    fn find_decoded(&self, input: &ScanInput) -> Vec<Match> {
+        let mut found = Vec::new();
+        // (text, encodings it was decoded from, location of the outermost encoded text)
+        let mut layer: Vec<(String, Vec<Encoding>, Option<Location>)> =
+            vec![(input.text.clone(), Vec::new(), None)];
+
+        for _ in 0..self.options.decode_depth {
+            let mut next_layer = Vec::new();
+            for (text, encodings, location) in &layer {
+                for decoded in decode_all(text) {
+                    let location = location.clone()
+                        .unwrap_or_else(|| input.locate(decoded.range.clone()));
+                    let mut chain = encodings.clone();
+                    chain.extend(decoded.encodings);
+
+                    for mut m in self.find_patterns(&ScanInput::from_text(&decoded.text)) {
+                        m.location = location.clone();
+                        m.decoded = chain.clone();
+                        found.push(m);
+                    }
+                    next_layer.push((decoded.text, chain, Some(location)));
+                }
+            }
+            layer = next_layer;
         }
+        found
     }
 
-    printable_text
-}
\ No newline at end of file
+    fn find_pattern(&self, input: &ScanInput, r: &PatternData, re: &PatternRegex) -> Vec<Match> {
+        // Find all matches for this pattern
+        re.find_ranges(&input.text)
+            .into_iter()
+            .map(|range| (range.clone(), r.validator.and_then(|v| v.validate(&input.text[range]))))
+            .filter(|&(_, validated)| !self.filter.validated_only || validated != Some(false))
+            .map(|(range, validated)| Match {
+                validated,
+                ..Match::new(r, input.text[range.clone()].to_string(), input.locate(range))
+            })
+            .collect()
+    }
+}
+
+/// Whether `bytes`, the start of a file, are text: UTF-8 without NUL bytes.
+/// Unless `complete`, they may end in the middle of a character.
+fn is_text(bytes: &[u8], complete: bool) -> bool {
+    if bytes.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none() && !complete,
+    }
+}
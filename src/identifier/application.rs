@@ -0,0 +1,340 @@
+use crate::identifier::decode::{inflate, percent_decode, to_text, Encoding};
+use once_cell::sync::Lazy;
+use regex::bytes::Regex;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::ops::Range;
+
+/// A field of an application protocol message, named like in Wireshark's display filters.
+pub(crate) struct Field {
+    pub name: &'static str,
+    /// Byte range of the field in the data it was parsed from.
+    pub range: Range<usize>,
+    /// The value of fields whose bytes aren't readable as they are,
+    /// e.g. DNS names or compressed HTTP bodies, along with how it was encoded.
+    pub decoded: Option<(Vec<Encoding>, String)>,
+}
+
+impl Field {
+    fn new(name: &'static str, range: Range<usize>) -> Self {
+        Field { name, range, decoded: None }
+    }
+
+    fn decoded(name: &'static str, range: Range<usize>, encodings: Vec<Encoding>, text: String) -> Self {
+        Field { name, range, decoded: Some((encodings, text)) }
+    }
+}
+
+/// The innermost field containing `range`.
+pub(crate) fn field_of<'a>(fields: &'a [Field], range: &Range<usize>) -> Option<&'a Field> {
+    fields.iter()
+        .filter(|f| f.range.start <= range.start && range.end <= f.range.end)
+        .min_by_key(|f| f.range.len())
+}
+
+/// Fields of the HTTP/1.x messages and the TLS ClientHello in a reassembled TCP stream.
+pub(crate) fn stream_fields(data: &[u8]) -> Vec<Field> {
+    let mut fields = http_fields(data);
+    fields.extend(server_name(data));
+    fields
+}
+
+static HTTP_START_LINE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)^(?:(?:GET|POST|PUT|DELETE|HEAD|OPTIONS|PATCH|CONNECT|TRACE) (\S+) HTTP/1\.[01]|HTTP/1\.[01] [0-9]{3}[^\r\n]*)\r?\n").unwrap()
+});
+
+fn http_fields(data: &[u8]) -> Vec<Field> {
+    let mut fields = Vec::new();
+    let mut position = 0;
+    // streams may start in the middle of a message, so messages are looked for
+    while let Some(start_line) = HTTP_START_LINE.captures_at(data, position) {
+        let is_request = start_line.get(1).is_some();
+        if let Some(target) = start_line.get(1) {
+            fields.extend(uri_fields(data, target.range()));
+        }
+        position = http_message(data, start_line.get(0).unwrap().end(), is_request, &mut fields);
+    }
+    fields
+}
+
+fn uri_fields(data: &[u8], target: Range<usize>) -> Vec<Field> {
+    let Some(question_mark) = data[target.clone()].iter().position(|&b| b == b'?') else {
+        return vec![Field::new("http.request.uri", target)];
+    };
+    let query = target.start + question_mark + 1..target.end;
+    let query_field = match data[query.clone()].contains(&b'%') {
+        true => match to_text(percent_decode(&data[query.clone()])) {
+            Some(text) => Field::decoded("http.request.uri.query", query, vec![Encoding::Percent], text),
+            None => Field::new("http.request.uri.query", query),
+        },
+        false => Field::new("http.request.uri.query", query),
+    };
+    vec![Field::new("http.request.uri", target.start..target.start + question_mark), query_field]
+}
+
+/// Adds the fields of the headers and body of an HTTP message starting at `start`,
+/// returns where the message ends.
+fn http_message(data: &[u8], start: usize, is_request: bool, fields: &mut Vec<Field>) -> usize {
+    let mut position = start;
+    let mut content_length = None;
+    let (mut chunked, mut content_encoding) = (false, None);
+
+    // headers, up to the empty line
+    loop {
+        let Some(line_len) = data[position..].iter().position(|&b| b == b'\n') else {
+            return data.len();
+        };
+        let line = position..position + line_len;
+        position += line_len + 1;
+        let header = trim_cr(&data[line.clone()]);
+        if header.is_empty() {
+            break;
+        }
+        let Some(colon) = header.iter().position(|&b| b == b':') else {
+            continue;
+        };
+        let name = String::from_utf8_lossy(&header[..colon]).to_lowercase();
+        let value = String::from_utf8_lossy(&header[colon + 1..]).trim().to_lowercase();
+        match name.as_str() {
+            "content-length" => content_length = value.parse::<usize>().ok(),
+            "transfer-encoding" => chunked = value.contains("chunked"),
+            "content-encoding" => content_encoding = Some(value),
+            _ => {}
+        }
+        let field_name = match name.as_str() {
+            "authorization" => "http.authorization",
+            "proxy-authorization" => "http.proxy_authorization",
+            "cookie" => "http.cookie",
+            "set-cookie" => "http.set_cookie",
+            "host" => "http.host",
+            "user-agent" => "http.user_agent",
+            "referer" => "http.referer",
+            "location" => "http.location",
+            _ if is_request => "http.request.line",
+            _ => "http.response.line",
+        };
+        fields.push(Field::new(field_name, line.start..line.start + header.len()));
+    }
+
+    // requests without a length have no body, responses without one last until the connection ends
+    let (body, mut bytes, end) = if chunked {
+        let (bytes, end) = dechunk(data, position);
+        (position..end, bytes, end)
+    } else {
+        let end = match content_length {
+            Some(length) => data.len().min(position.saturating_add(length)),
+            None if is_request => position,
+            None => data.len(),
+        };
+        (position..end, data[position..end].to_vec(), end)
+    };
+    if body.is_empty() {
+        return end;
+    }
+
+    let mut encodings = Vec::new();
+    match content_encoding.as_deref() {
+        Some("gzip" | "x-gzip") => encodings.push(Encoding::Gzip),
+        Some("deflate") => encodings.push(Encoding::Deflate),
+        _ => {}
+    }
+    if let Some(&encoding) = encodings.first() {
+        match inflate(encoding, &bytes) {
+            Some(inflated) => bytes = inflated,
+            None => encodings.clear(),
+        }
+    }
+    let field = match (chunked || !encodings.is_empty(), to_text(bytes)) {
+        (true, Some(text)) => Field::decoded("http.file_data", body, encodings, text),
+        _ => Field::new("http.file_data", body),
+    };
+    fields.push(field);
+    end
+}
+
+/// The data of a chunked body starting at `start`, along with where the body ends.
+fn dechunk(data: &[u8], start: usize) -> (Vec<u8>, usize) {
+    let mut body = Vec::new();
+    let mut position = start;
+    loop {
+        let Some(line_len) = data[position..].iter().position(|&b| b == b'\n') else {
+            return (body, data.len());
+        };
+        let size_line = String::from_utf8_lossy(trim_cr(&data[position..position + line_len]));
+        // chunk extensions follow a semicolon
+        let size = size_line.split(';').next().unwrap_or("").trim();
+        let Ok(size) = usize::from_str_radix(size, 16) else {
+            return (body, position);
+        };
+        position += line_len + 1;
+        if size == 0 {
+            // the trailer ends with an empty line
+            while let Some(line_len) = data[position..].iter().position(|&b| b == b'\n') {
+                position += line_len + 1;
+                if line_len <= 1 {
+                    break;
+                }
+            }
+            return (body, position);
+        }
+        let end = data.len().min(position.saturating_add(size));
+        body.extend_from_slice(&data[position..end]);
+        position = data.len().min(end + 2);
+    }
+}
+
+fn trim_cr(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// The server name of a TLS ClientHello at the start of `data`.
+fn server_name(data: &[u8]) -> Option<Field> {
+    // record header: handshake, TLS 1.x and length, then the handshake header of a ClientHello
+    if data.len() < 9 || data[0] != 0x16 || data[1] != 3 || data[5] != 1 {
+        return None;
+    }
+    let mut reader = Reader { data, position: 9 };
+    reader.skip(2 + 32)?; // version and random
+    let session_id_len = reader.u8()? as usize;
+    reader.skip(session_id_len)?;
+    let cipher_suites_len = reader.u16()? as usize;
+    reader.skip(cipher_suites_len)?;
+    let compression_methods_len = reader.u8()? as usize;
+    reader.skip(compression_methods_len)?;
+    let extensions_end = reader.u16()? as usize + reader.position;
+
+    while reader.position < extensions_end {
+        let extension_type = reader.u16()?;
+        let extension_len = reader.u16()? as usize;
+        if extension_type != 0 {
+            reader.skip(extension_len)?;
+            continue;
+        }
+        // server name list, of which only host names are in use
+        reader.skip(2)?;
+        if reader.u8()? != 0 {
+            return None;
+        }
+        let name_len = reader.u16()? as usize;
+        let start = reader.position;
+        reader.skip(name_len)?;
+        return Some(Field::new("tls.handshake.extensions_server_name", start..start + name_len));
+    }
+    None
+}
+
+/// Fields of a DNS message in a UDP payload, the names asked for and the answers.
+pub(crate) fn dns_fields(payload: &[u8]) -> Vec<Field> {
+    dns_message(payload).unwrap_or_default()
+}
+
+fn dns_message(payload: &[u8]) -> Option<Vec<Field>> {
+    let mut reader = Reader { data: payload, position: 4 };
+    let questions = reader.u16()?;
+    let answers = reader.u16()?;
+    reader.skip(4)?; // authority and additional records
+
+    let mut fields = Vec::new();
+    for _ in 0..questions {
+        let (name, range) = reader.dns_name()?;
+        fields.push(Field::decoded("dns.qry.name", range, Vec::new(), name));
+        reader.skip(4)?; // type and class
+    }
+    for _ in 0..answers {
+        let (name, range) = reader.dns_name()?;
+        fields.push(Field::decoded("dns.resp.name", range, Vec::new(), name));
+        let record_type = reader.u16()?;
+        reader.skip(6)?; // class and time to live
+        let data_len = reader.u16()? as usize;
+        let data = reader.position..reader.position + data_len;
+        let record = payload.get(data.clone())?;
+        let field = match record_type {
+            1 if data_len == 4 => {
+                let address = Ipv4Addr::from(<[u8; 4]>::try_from(record).ok()?);
+                Some(Field::decoded("dns.a", data.clone(), Vec::new(), address.to_string()))
+            }
+            28 if data_len == 16 => {
+                let address = Ipv6Addr::from(<[u8; 16]>::try_from(record).ok()?);
+                Some(Field::decoded("dns.aaaa", data.clone(), Vec::new(), address.to_string()))
+            }
+            5 | 12 => {
+                let name = if record_type == 5 { "dns.cname" } else { "dns.ptr.domain_name" };
+                let (target, _) = Reader { data: payload, position: data.start }.dns_name()?;
+                Some(Field::decoded(name, data.clone(), Vec::new(), target))
+            }
+            16 => {
+                // one or more length-prefixed strings
+                let mut strings = Vec::new();
+                let mut txt = Reader { data: record, position: 0 };
+                while txt.position < record.len() {
+                    let len = txt.u8()? as usize;
+                    let start = txt.position;
+                    txt.skip(len)?;
+                    strings.push(String::from_utf8_lossy(&record[start..start + len]).into_owned());
+                }
+                Some(Field::decoded("dns.txt", data.clone(), Vec::new(), strings.concat()))
+            }
+            _ => None,
+        };
+        fields.extend(field);
+        reader.skip(data_len)?;
+    }
+    Some(fields)
+}
+
+/// Reads big endian numbers and DNS names from `data`, `None` once they run past its end.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn skip(&mut self, len: usize) -> Option<()> {
+        let end = self.position.checked_add(len)?;
+        (end <= self.data.len()).then(|| self.position = end)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        let value = *self.data.get(self.position)?;
+        self.position += 1;
+        Some(value)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from(self.u8()?) << 8 | u16::from(self.u8()?))
+    }
+
+    /// A domain name, following compression pointers, and the range of its labels
+    /// up to the first pointer.
+    fn dns_name(&mut self) -> Option<(String, Range<usize>)> {
+        const MAX_POINTERS: usize = 16;
+
+        let start = self.position;
+        let mut labels: Vec<String> = Vec::new();
+        let mut position = self.position;
+        let mut end = None;
+        for _ in 0..MAX_POINTERS {
+            loop {
+                let len = *self.data.get(position)? as usize;
+                match len {
+                    0 => {
+                        let end = end.unwrap_or(position + 1);
+                        self.position = end;
+                        return Some((labels.join("."), start..end));
+                    }
+                    0xc0.. => {
+                        let pointer = (len & 0x3f) << 8 | *self.data.get(position + 1)? as usize;
+                        end.get_or_insert(position + 2);
+                        position = pointer;
+                        break;
+                    }
+                    _ => {
+                        let label = self.data.get(position + 1..position + 1 + len)?;
+                        labels.push(String::from_utf8_lossy(label).into_owned());
+                        position += 1 + len;
+                    }
+                }
+            }
+        }
+        None
+    }
+}
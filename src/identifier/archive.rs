@@ -0,0 +1,353 @@
+use crate::identifier::location::ScanInput;
+use crate::identifier::signature::find_format_signatures;
+use crate::identifier::{Identifier, Match, Source};
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+// Tells the format of a file, the magic number of tar is at offset 257
+const HEAD_LEN: usize = 512;
+// Covers the signatures of the archive itself, some are far into the file
+const SIGNATURE_HEAD_LEN: u64 = 64 << 10;
+// Decompressed bytes before the compression ratio is checked, small files compress well
+const RATIO_GRACE: u64 = 1 << 20;
+// Nested zip files up to this size are read into memory, larger ones into a temporary file
+const MAX_IN_MEMORY_ZIP: u64 = 16 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Zip,
+    Tar,
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Format {
+    /// The archive or compression format `head`, the start of a file, is in.
+    fn detect(head: &[u8]) -> Option<Format> {
+        const BZIP2_BLOCK: [u8; 6] = [0x31, 0x41, 0x59, 0x26, 0x53, 0x59];
+        const BZIP2_END: [u8; 6] = [0x17, 0x72, 0x45, 0x38, 0x50, 0x90];
+
+        if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+            Some(Format::Zip)
+        } else if head.starts_with(&[0x1f, 0x8b, 0x08]) {
+            Some(Format::Gzip)
+        } else if head.starts_with(b"BZh")
+            && head.get(3).is_some_and(|level| (b'1'..=b'9').contains(level))
+            && head.get(4..10).is_some_and(|block| block == BZIP2_BLOCK || block == BZIP2_END) {
+            Some(Format::Bzip2)
+        } else if head.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Some(Format::Xz)
+        } else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Format::Zstd)
+        } else if head.get(257..262) == Some(b"ustar") {
+            Some(Format::Tar)
+        } else {
+            None
+        }
+    }
+
+    /// Extensions of compressed files, without them a name is the one of the decompressed file.
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Format::Gzip => &[".gz", ".gzip"],
+            Format::Bzip2 => &[".bz2", ".bzip2"],
+            Format::Xz => &[".xz"],
+            Format::Zstd => &[".zst", ".zstd"],
+            Format::Zip | Format::Tar => &[],
+        }
+    }
+}
+
+/// Whether the file at `path` is an archive or compressed file.
+pub(crate) fn is_archive(path: &Path) -> io::Result<bool> {
+    let mut head = Vec::with_capacity(HEAD_LEN);
+    File::open(path)?.take(HEAD_LEN as u64).read_to_end(&mut head)?;
+    Ok(Format::detect(&head).is_some())
+}
+
+/// Identifies the files in the archive or compressed file at `path`, along with the
+/// signature of the archive itself.
+///
+/// Archives nested in it are looked into as well, up to [`Options::archive_depth`](crate::Options)
+/// levels deep, and their files are reported as `bundle.zip!/inner.tar.gz!/config.yml`.
+/// A compressed tar is one level, its files are `bundle.tar.gz!/config.yml`.
+///
+/// Decompressing stops after [`Options::max_decompressed`](crate::Options) bytes,
+/// and files compressed better than [`Options::max_compression_ratio`](crate::Options)
+/// are skipped, so archive bombs can't exhaust memory or time.
+pub(crate) fn identify_archive(
+    identifier: &Identifier,
+    path: &Path,
+    matches: &mut Vec<Match>,
+) -> anyhow::Result<()> {
+    let mut file = File::open(path)?;
+    let mut head = Vec::new();
+    (&mut file).take(SIGNATURE_HEAD_LEN).read_to_end(&mut head)?;
+    let source = Source::File { path: path.to_path_buf() };
+    let input = ScanInput::from_ranges(source, &head, &[]);
+    matches.extend(find_format_signatures(identifier, &input, &head));
+
+    let Some(format) = Format::detect(&head) else {
+        return Ok(());
+    };
+    file.rewind()?;
+    let mut scan = ArchiveScan {
+        identifier,
+        path,
+        budget: Rc::new(Cell::new(identifier.options.max_decompressed)),
+        stopped: false,
+    };
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let result = match format {
+        // only zip files on disk can seek to their central directory without buffering
+        Format::Zip => scan.identify_zip(file, "", 1, matches),
+        _ => scan.identify_archive(format, &mut file, &name, "", 1, matches),
+    };
+    if let Err(e) = result {
+        eprintln!("Skipping rest of {}: {:#}", path.display(), e);
+    }
+    Ok(())
+}
+
+/// What looking into an archive keeps track of.
+struct ArchiveScan<'a> {
+    identifier: &'a Identifier,
+    path: &'a Path,
+    /// Decompressed bytes left, shared with the readers decompressing.
+    budget: Rc<Cell<u64>>,
+    /// The budget ran out, the rest of the archive is skipped.
+    stopped: bool,
+}
+
+impl ArchiveScan<'_> {
+    /// Identifies the files of the archive in `format` read from `reader`.
+    /// `name` is the name of the archive and `prefix` is the path of the archive it's in.
+    fn identify_archive(
+        &mut self,
+        format: Format,
+        reader: &mut dyn Read,
+        name: &str,
+        prefix: &str,
+        depth: usize,
+        matches: &mut Vec<Match>,
+    ) -> anyhow::Result<()> {
+        match format {
+            Format::Zip => {
+                // a nested zip file has to be stored to seek in it, bounded by the budget
+                let mut reader = reader.take(self.budget.get());
+                let mut data = Vec::new();
+                (&mut reader).take(MAX_IN_MEMORY_ZIP + 1).read_to_end(&mut data)?;
+                if data.len() as u64 <= MAX_IN_MEMORY_ZIP {
+                    return self.identify_zip(Cursor::new(data), prefix, depth, matches);
+                }
+                let mut file = tempfile::tempfile()?;
+                file.write_all(&data)?;
+                drop(data);
+                io::copy(&mut reader, &mut file)?;
+                file.rewind()?;
+                self.identify_zip(file, prefix, depth, matches)
+            }
+            Format::Tar => self.identify_tar(reader, prefix, depth, matches),
+            Format::Gzip | Format::Bzip2 | Format::Xz | Format::Zstd => {
+                let consumed = Rc::new(Cell::new(0));
+                let counted = Counting { inner: reader, count: consumed.clone() };
+                let decoder: Box<dyn Read> = match format {
+                    Format::Gzip => Box::new(flate2::read::MultiGzDecoder::new(counted)),
+                    Format::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(counted)),
+                    Format::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(counted)),
+                    _ => Box::new(zstd::stream::read::Decoder::new(counted)?),
+                };
+                let mut decompressed = self.limited(decoder, consumed);
+
+                let head = read_head(&mut decompressed)?;
+                let mut decompressed = Cursor::new(head).chain(decompressed);
+                if Format::detect(decompressed.get_ref().0.get_ref()) == Some(Format::Tar) {
+                    // a compressed tar is a single archive
+                    return self.identify_tar(&mut decompressed, prefix, depth, matches);
+                }
+                let extension = format.extensions().iter()
+                    .find(|extension| name.to_lowercase().ends_with(*extension));
+                let entry = match extension {
+                    Some(extension) => &name[..name.len() - extension.len()],
+                    None => name,
+                };
+                self.identify_entry(entry, &mut decompressed, prefix, depth, matches)
+            }
+        }
+    }
+
+    fn identify_zip(
+        &mut self,
+        reader: impl Read + Seek,
+        prefix: &str,
+        depth: usize,
+        matches: &mut Vec<Match>,
+    ) -> anyhow::Result<()> {
+        // the compressed bytes actually read, headers can't be trusted with the ratio check
+        let consumed = Rc::new(Cell::new(0));
+        let mut zip = zip::ZipArchive::new(Counting { inner: reader, count: consumed.clone() })?;
+        for i in 0..zip.len() {
+            if self.stopped {
+                break;
+            }
+            let mut file = match zip.by_index(i) {
+                Ok(file) => file,
+                Err(e) => {
+                    eprintln!("Skipping file {} of {}{}: {}", i, self.path.display(), prefix, e);
+                    continue;
+                }
+            };
+            if file.is_dir() {
+                continue;
+            }
+            let name = file.name().to_string();
+            consumed.set(0);
+            let mut reader = self.limited(&mut file, consumed.clone());
+            self.identify_entry(&name, &mut reader, prefix, depth, matches)?;
+        }
+        Ok(())
+    }
+
+    fn identify_tar(
+        &mut self,
+        reader: &mut dyn Read,
+        prefix: &str,
+        depth: usize,
+        matches: &mut Vec<Match>,
+    ) -> anyhow::Result<()> {
+        let mut tar = tar::Archive::new(reader);
+        for entry in tar.entries()? {
+            if self.stopped {
+                break;
+            }
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry.path()?.to_string_lossy().to_string();
+            self.identify_entry(&name, &mut entry, prefix, depth, matches)?;
+        }
+        Ok(())
+    }
+
+    /// Identifies the file `name` of an archive, or the files in it if it's an archive too.
+    /// Errors reading the file itself skip it, errors of the archive it's in are returned.
+    fn identify_entry(
+        &mut self,
+        name: &str,
+        reader: &mut dyn Read,
+        prefix: &str,
+        depth: usize,
+        matches: &mut Vec<Match>,
+    ) -> anyhow::Result<()> {
+        if self.budget.get() == 0 {
+            eprintln!("Skipping rest of {}: decompressed more than {} bytes",
+                self.path.display(), self.identifier.options.max_decompressed);
+            self.stopped = true;
+            return Ok(());
+        }
+
+        let result = read_head(reader).and_then(|head| {
+            let format = Format::detect(&head)
+                .filter(|_| depth < self.identifier.options.archive_depth);
+            let mut reader = Cursor::new(head).chain(reader);
+            match format {
+                Some(format) => {
+                    let nested = format!("{}{}!/", prefix, name);
+                    self.identify_archive(format, &mut reader, name, &nested, depth + 1, matches)
+                }
+                None => {
+                    let source = Source::Archive {
+                        path: self.path.to_path_buf(),
+                        entry: format!("{}{}", prefix, name),
+                    };
+                    self.identifier.identify_windows(source, reader, matches)
+                }
+            }
+        });
+        if let Err(e) = result {
+            eprintln!("Skipping {}: {:#}", self.display(prefix, name), e);
+        }
+        Ok(())
+    }
+
+    /// Counts what `reader` decompresses against the limits, `consumed` is what it read so far.
+    fn limited<R: Read>(&self, reader: R, consumed: Rc<Cell<u64>>) -> Limited<R> {
+        Limited {
+            inner: reader,
+            consumed,
+            produced: 0,
+            budget: self.budget.clone(),
+            max_decompressed: self.identifier.options.max_decompressed,
+            max_ratio: self.identifier.options.max_compression_ratio,
+        }
+    }
+
+    fn display(&self, prefix: &str, name: &str) -> String {
+        format!("{}!/{}{}", self.path.display(), prefix, name)
+    }
+}
+
+/// Reads up to [`HEAD_LEN`] bytes to tell the format of a file.
+fn read_head(reader: &mut dyn Read) -> anyhow::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(HEAD_LEN);
+    reader.take(HEAD_LEN as u64).read_to_end(&mut head)?;
+    Ok(head)
+}
+
+/// Counts the bytes read from a compressed stream.
+struct Counting<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for Counting<R> {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(position)
+    }
+}
+
+/// Decompressed data, failing once the budget of decompressed bytes is used up
+/// or the compression ratio gets too high.
+struct Limited<R> {
+    inner: R,
+    /// Compressed bytes read so far.
+    consumed: Rc<Cell<u64>>,
+    produced: u64,
+    budget: Rc<Cell<u64>>,
+    max_decompressed: u64,
+    max_ratio: u64,
+}
+
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        let budget = self.budget.get();
+        if n as u64 > budget {
+            self.budget.set(0);
+            return Err(io::Error::other(format!(
+                "decompressed more than {} bytes", self.max_decompressed
+            )));
+        }
+        self.budget.set(budget - n as u64);
+        self.produced += n as u64;
+        if self.produced > RATIO_GRACE
+            && self.produced / self.consumed.get().max(1) > self.max_ratio {
+            return Err(io::Error::other(format!("compressed more than {} times", self.max_ratio)));
+        }
+        Ok(n)
+    }
+}
@@ -0,0 +1,303 @@
+use crate::details::format_utc_micros;
+use crate::identifier::application::{dns_fields, field_of, stream_fields, Field};
+use crate::identifier::location::ScanInput;
+use crate::identifier::packet::{dissect, Packet, Protocol};
+use crate::identifier::reassembly::{Reassembler, StreamChunk};
+use crate::identifier::{Identifier, Match, MatchedTexts, Source};
+use pcap_parser::pcapng::Block;
+use pcap_parser::traits::PcapNGPacketBlock;
+use pcap_parser::{create_reader, Linktype, PcapBlockOwned, PcapError};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+const READER_CAPACITY: usize = 1 << 16;
+// Fits the largest snapshot length capture tools use, 256 KiB
+const MAX_READER_CAPACITY: usize = 1 << 20;
+
+// DNS, multicast DNS and LLMNR
+const DNS_PORTS: [u16; 3] = [53, 5353, 5355];
+
+/// Magic numbers of pcapng, and of pcap with microsecond and nanosecond timestamps,
+/// in both byte orders.
+const CAPTURE_MAGICS: [[u8; 4]; 5] = [
+    [0x0a, 0x0d, 0x0d, 0x0a],
+    [0xd4, 0xc3, 0xb2, 0xa1],
+    [0xa1, 0xb2, 0xc3, 0xd4],
+    [0x4d, 0x3c, 0xb2, 0xa1],
+    [0xa1, 0xb2, 0x3c, 0x4d],
+];
+
+/// Whether the file at `path` starts like a pcap or pcapng capture.
+pub(crate) fn is_capture(path: &Path) -> io::Result<bool> {
+    let mut magic = [0; 4];
+    let mut file = File::open(path)?;
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(CAPTURE_MAGICS.contains(&magic)),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Link type and timestamp resolution of an interface of a pcapng section.
+struct Interface {
+    /// Index among the interfaces of all sections of the file.
+    index: usize,
+    linktype: Linktype,
+    /// Timestamp units per second, `None` if the interface gives an invalid one.
+    resolution: Option<u64>,
+    offset: i64,
+}
+
+/// A packet read from a capture.
+pub(crate) struct Frame<'a> {
+    /// 1-based packet number, as shown by Wireshark.
+    pub number: usize,
+    /// Interface the packet was captured on, counted across all sections of a pcapng file.
+    pub interface: usize,
+    pub linktype: Linktype,
+    pub data: &'a [u8],
+    /// Length of the packet on the wire, `data` may be cut off by the snapshot length.
+    pub original_len: u32,
+    /// Seconds and microseconds since the epoch.
+    pub timestamp: Option<(i64, u32)>,
+}
+
+/// Calls `on_frame` for every packet of the pcap or pcapng capture at `path`,
+/// the format is detected from the file's magic number.
+pub(crate) fn read_capture(path: &Path, mut on_frame: impl FnMut(&Frame)) -> anyhow::Result<()> {
+    if !is_capture(path)? {
+        anyhow::bail!("{:?} is neither a pcap nor a pcapng capture", path);
+    }
+    let file = File::open(path)?;
+    let mut reader = create_reader(READER_CAPACITY, file)
+        .map_err(|e| anyhow::anyhow!("Failed to read capture {:?}: {}", path, e))?;
+    let mut capacity = READER_CAPACITY;
+    let mut packet_index = 0;
+    // of a pcap file
+    let (mut linktype, mut nanosecond) = (Linktype::ETHERNET, false);
+    // of the current pcapng section
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut interface_count = 0;
+
+    loop {
+        match reader.next() {
+            Ok((offset, block)) => {
+                // (interface, link type, frame, original length, timestamp)
+                let frame = match &block {
+                    PcapBlockOwned::LegacyHeader(header) => {
+                        linktype = header.network;
+                        nanosecond = header.is_nanosecond_precision();
+                        None
+                    }
+                    PcapBlockOwned::Legacy(packet) => {
+                        let micros = if nanosecond { packet.ts_usec / 1000 } else { packet.ts_usec };
+                        let timestamp = Some((packet.ts_sec as i64, micros));
+                        Some((0, linktype, packet.data, packet.origlen, timestamp))
+                    }
+                    PcapBlockOwned::NG(Block::SectionHeader(_)) => {
+                        interfaces.clear();
+                        None
+                    }
+                    PcapBlockOwned::NG(Block::InterfaceDescription(description)) => {
+                        interfaces.push(Interface {
+                            index: interface_count,
+                            linktype: description.linktype,
+                            resolution: description.ts_resolution(),
+                            offset: description.ts_offset(),
+                        });
+                        interface_count += 1;
+                        None
+                    }
+                    PcapBlockOwned::NG(Block::EnhancedPacket(packet)) => {
+                        interfaces.get(packet.if_id as usize).map(|interface| {
+                            let timestamp = interface.resolution.map(|resolution| {
+                                let (seconds, fraction) =
+                                    packet.decode_ts(interface.offset as u64, resolution);
+                                let micros = fraction as u64 * 1_000_000 / resolution;
+                                (seconds as i64, micros as u32)
+                            });
+                            let data = packet.packet_data();
+                            (interface.index, interface.linktype, data, packet.origlen, timestamp)
+                        })
+                    }
+                    PcapBlockOwned::NG(Block::SimplePacket(packet)) => {
+                        // simple packets have no timestamp and always come from the first interface
+                        interfaces.first().map(|interface| {
+                            let data = packet.packet_data();
+                            (interface.index, interface.linktype, data, packet.origlen, None)
+                        })
+                    }
+                    // name resolution, statistics, ...
+                    _ => None,
+                };
+                if let Some((interface, linktype, data, original_len, timestamp)) = frame {
+                    packet_index += 1;
+                    on_frame(&Frame {
+                        number: packet_index,
+                        interface,
+                        linktype,
+                        data,
+                        original_len,
+                        timestamp,
+                    });
+                }
+                reader.consume(offset);
+            },
+            Err(PcapError::Eof) => break,
+            Err(PcapError::UnexpectedEof) => {
+                eprintln!("Capture {:?} is truncated after packet {}", path, packet_index);
+                break;
+            }
+            Err(PcapError::Incomplete(_)) => {
+                reader.refill()
+                    .map_err(|e| anyhow::anyhow!("Failed to read capture {:?}: {}", path, e))?;
+            },
+            Err(PcapError::BufferTooSmall) => {
+                capacity *= 2;
+                if capacity > MAX_READER_CAPACITY || !reader.grow(capacity) {
+                    anyhow::bail!("Capture {:?} has a block larger than {} bytes",
+                        path, MAX_READER_CAPACITY);
+                }
+                reader.refill()
+                    .map_err(|e| anyhow::anyhow!("Failed to read capture {:?}: {}", path, e))?;
+            }
+            Err(e) => anyhow::bail!("Failed to read capture {:?}: {}", path, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Identifies the payloads of the packets of a pcap or pcapng capture.
+///
+/// Frames are dissected down to their TCP or UDP payload, or whatever IP carries,
+/// those that can't be dissected are identified as a whole.
+/// TCP payloads are reassembled into streams first, so matches split between segments are found,
+/// and located in the packet they start in.
+/// Matches in HTTP/1.x messages, DNS messages and TLS ClientHellos are named after their field,
+/// and compressed HTTP bodies and DNS names are identified in their decoded form.
+/// Packets excluded by the packet filters of the [`Filter`](crate::Filter) are skipped.
+pub(crate) fn identify_capture(
+    identifier: &Identifier,
+    path: &Path,
+    matches: &mut Vec<Match>,
+) -> anyhow::Result<()> {
+    let mut scan = CaptureScan {
+        identifier,
+        path,
+        matched_texts: MatchedTexts::default(),
+        streams: Reassembler::default(),
+    };
+    read_capture(path, |frame| scan.identify_frame(frame, matches))?;
+    scan.finish(matches);
+    Ok(())
+}
+
+/// What identifying the packets of a capture keeps track of between packets.
+struct CaptureScan<'a> {
+    identifier: &'a Identifier,
+    path: &'a Path,
+    matched_texts: MatchedTexts,
+    streams: Reassembler,
+}
+
+impl CaptureScan<'_> {
+    /// Identifies the payload of a frame, or adds it to its TCP stream.
+    fn identify_frame(&mut self, frame: &Frame, matches: &mut Vec<Match>) {
+        let Frame { number, linktype, data: frame, timestamp, .. } = *frame;
+        let dissected = dissect(linktype, frame);
+        let packet = Packet {
+            number,
+            timestamp: timestamp.map(|(seconds, micros)| format_utc_micros(seconds, micros)),
+            protocol: dissected.as_ref().map(|d| d.protocol),
+            src: dissected.as_ref().map(|d| d.src),
+            dst: dissected.as_ref().map(|d| d.dst),
+            src_port: dissected.as_ref().and_then(|d| d.src_port),
+            dst_port: dissected.as_ref().and_then(|d| d.dst_port),
+        };
+        if self.identifier.filter.gets_excluded_packet(&packet) {
+            return;
+        }
+
+        let payload = dissected.as_ref().map_or(0..frame.len(), |d| d.payload.clone());
+        if let Some(header) = dissected.and_then(|d| d.tcp) {
+            self.streams.add(&packet, &header, &frame[payload.clone()], payload.start);
+            for chunk in self.streams.take_ready() {
+                self.identify_stream_chunk(&chunk, matches);
+            }
+            return;
+        }
+
+        let is_dns = packet.protocol == Some(Protocol::Udp)
+            && [packet.src_port, packet.dst_port].iter().flatten().any(|p| DNS_PORTS.contains(p));
+        let fields = if is_dns { dns_fields(&frame[payload.clone()]) } else { Vec::new() };
+        let source = Source::Packet { path: self.path.to_path_buf(), index: number };
+        let input = ScanInput::from_lossy(source, &frame[payload.clone()])
+            .by_line()
+            .starting_at(payload.start, 0);
+        let mut found = self.find_in_fields(&input, payload.start, &fields);
+        for m in &mut found {
+            m.packet = Some(packet.clone());
+        }
+        self.identifier.add_matches(found, &mut self.matched_texts, matches);
+    }
+
+    /// Identifies a chunk of a reassembled TCP stream, locating matches in the packet
+    /// they start in, with lines and columns counted from the start of its payload.
+    fn identify_stream_chunk(&mut self, chunk: &StreamChunk, matches: &mut Vec<Match>) {
+        let path = self.path;
+        let source = Source::Packet { path: path.to_path_buf(), index: chunk.first_packet().number };
+        let input = ScanInput::from_lossy(source, &chunk.data).by_line();
+        let found: Vec<Match> = self.find_in_fields(&input, 0, &stream_fields(&chunk.data))
+            .into_iter()
+            // matches starting outside were or will be found with the previous or next chunk
+            .filter(|m| chunk.owned.contains(&m.location.start))
+            .map(|mut m| {
+                let (packet, start, segment_start) = chunk.locate(m.location.start);
+                let before = &chunk.data[segment_start..m.location.start];
+                let line_start = before.iter().rposition(|&b| b == b'\n').map_or(0, |n| n + 1);
+                m.location.source = Source::Packet { path: path.to_path_buf(), index: packet.number };
+                m.location.end = start + (m.location.end - m.location.start);
+                m.location.start = start;
+                m.location.line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+                m.location.column = String::from_utf8_lossy(&before[line_start..]).chars().count() + 1;
+                m.packet = Some(packet.clone());
+                m
+            })
+            .collect();
+        self.identifier.add_matches(found, &mut self.matched_texts, matches);
+    }
+
+    /// Matches in `input` and in the decoded values of `fields`, naming the field of each match.
+    /// The ranges of the fields are offsets into the content of `input`, which starts at `offset`.
+    fn find_in_fields(&self, input: &ScanInput, offset: usize, fields: &[Field]) -> Vec<Match> {
+        let mut found = self.identifier.find_all(input);
+        for m in &mut found {
+            let range = m.location.start - offset..m.location.end - offset;
+            m.field = field_of(fields, &range).map(|field| field.name.to_string());
+        }
+
+        for field in fields {
+            let Some((encodings, text)) = &field.decoded else {
+                continue;
+            };
+            let location = input.locate_content(field.range.clone());
+            for mut m in self.identifier.find_all(&ScanInput::from_text(text)) {
+                m.location = location.clone();
+                m.decoded = encodings.iter().copied().chain(m.decoded.drain(..)).collect();
+                m.field = Some(field.name.to_string());
+                found.push(m);
+            }
+        }
+        found
+    }
+
+    /// Identifies what's left of the TCP streams at the end of the capture.
+    fn finish(mut self, matches: &mut Vec<Match>) {
+        let streams = std::mem::take(&mut self.streams);
+        for chunk in streams.finish() {
+            self.identify_stream_chunk(&chunk, matches);
+        }
+    }
+}
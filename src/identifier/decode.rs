@@ -0,0 +1,169 @@
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::fmt;
+use std::io::Read;
+use std::ops::Range;
+
+/// How a piece of text was encoded inside the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    Base64,
+    Base64Url,
+    Hex,
+    /// URL percent-encoding.
+    Percent,
+    /// gzip compressed, looked for inside base64 and in HTTP bodies.
+    Gzip,
+    /// zlib or raw deflate compressed HTTP bodies.
+    Deflate,
+    /// Strings of binary files in UTF-16, little endian.
+    Utf16Le,
+    /// Strings of binary files in UTF-16, big endian.
+    Utf16Be,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Encoding::Base64 => write!(f, "base64"),
+            Encoding::Base64Url => write!(f, "base64url"),
+            Encoding::Hex => write!(f, "hex"),
+            Encoding::Percent => write!(f, "percent"),
+            Encoding::Gzip => write!(f, "gzip"),
+            Encoding::Deflate => write!(f, "deflate"),
+            Encoding::Utf16Le => write!(f, "utf16le"),
+            Encoding::Utf16Be => write!(f, "utf16be"),
+        }
+    }
+}
+
+/// Text decoded from the substring `range` of the text it was found in.
+pub(crate) struct Decoded {
+    pub range: Range<usize>,
+    pub encodings: Vec<Encoding>,
+    pub text: String,
+}
+
+// 8 characters is `test.com` in base64, the shorter the run the more likely it is a word
+static BASE64_OR_HEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[A-Za-z0-9+/_-]{8,}={0,2}").unwrap()
+});
+static PERCENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[^\s%]*(?:%[0-9A-Fa-f]{2}[^\s%]*)+").unwrap()
+});
+
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+const BASE64_URL: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+// gzip bombs stop being inflated here
+const MAX_INFLATED_LEN: u64 = 16 << 20;
+
+/// Encoded substrings of `text` that decode to text, in order of their position.
+///
+/// Decodings that result in binary data are dropped, so random words and hashes
+/// that happen to be valid base64 or hex don't turn up.
+pub(crate) fn decode_all(text: &str) -> Vec<Decoded> {
+    let mut decoded: Vec<Decoded> = BASE64_OR_HEX.find_iter(text)
+        .filter_map(|m| decode_base64_or_hex(m.as_str()).map(|(encodings, text)| Decoded {
+            range: m.range(),
+            encodings,
+            text,
+        }))
+        .chain(PERCENT.find_iter(text).filter_map(|m| {
+            let text = to_text(percent_decode(m.as_str().as_bytes()))?;
+            Some(Decoded { range: m.range(), encodings: vec![Encoding::Percent], text })
+        }))
+        .collect();
+    decoded.sort_by_key(|d| d.range.start);
+    decoded
+}
+
+fn decode_base64_or_hex(encoded: &str) -> Option<(Vec<Encoding>, String)> {
+    // hex digits are base64 characters too, but hex is far more likely
+    if encoded.len().is_multiple_of(2) && encoded.bytes().all(|b| b.is_ascii_hexdigit()) {
+        let bytes = hex_decode(encoded)?;
+        return to_text(bytes).map(|text| (vec![Encoding::Hex], text));
+    }
+
+    let url_safe = encoded.contains(['-', '_']);
+    if url_safe && encoded.contains(['+', '/']) {
+        return None;
+    }
+    let (engine, encoding) = if url_safe {
+        (&BASE64_URL, Encoding::Base64Url)
+    } else {
+        (&BASE64, Encoding::Base64)
+    };
+    let bytes = engine.decode(encoded).ok()?;
+
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let inflated = inflate(Encoding::Gzip, &bytes)?;
+        return to_text(inflated).map(|text| (vec![encoding, Encoding::Gzip], text));
+    }
+    to_text(bytes).map(|text| (vec![encoding], text))
+}
+
+fn hex_decode(encoded: &str) -> Option<Vec<u8>> {
+    (0..encoded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&encoded[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Decompresses gzip or deflate compressed `bytes`, `None` for other encodings.
+pub(crate) fn inflate(encoding: Encoding, bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut inflated = Vec::new();
+    let read = match encoding {
+        Encoding::Gzip => GzDecoder::new(bytes).take(MAX_INFLATED_LEN).read_to_end(&mut inflated),
+        // servers send raw deflate as often as the zlib format the name stands for
+        Encoding::Deflate if bytes.first().is_some_and(|&b| b & 0x0f == 8) => {
+            ZlibDecoder::new(bytes).take(MAX_INFLATED_LEN).read_to_end(&mut inflated)
+        }
+        Encoding::Deflate => {
+            DeflateDecoder::new(bytes).take(MAX_INFLATED_LEN).read_to_end(&mut inflated)
+        }
+        _ => return None,
+    };
+    read.ok().map(|_| inflated)
+}
+
+pub(crate) fn percent_decode(bytes: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// `bytes` as a string if they are UTF-8 without control characters other than whitespace.
+pub(crate) fn to_text(bytes: Vec<u8>) -> Option<String> {
+    let text = String::from_utf8(bytes).ok()?;
+    let is_text = !text.is_empty()
+        && text.chars().all(|c| !c.is_control() || c.is_ascii_whitespace());
+    is_text.then_some(text)
+}
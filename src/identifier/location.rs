@@ -0,0 +1,362 @@
+use serde::Serialize;
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// The input a match was found in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Source {
+    /// Text passed in directly.
+    Text,
+    /// Text read from the standard input.
+    Stdin,
+    File { path: PathBuf },
+    /// A packet of a capture file, `index` is the 1-based packet number as shown by Wireshark.
+    Packet { path: PathBuf, index: usize },
+    /// A file in an archive or compressed file, `entry` is its path in the archive.
+    /// Paths in nested archives are joined with `!/`, e.g. `inner.zip!/config.yml`.
+    Archive { path: PathBuf, entry: String },
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Text => write!(f, "text"),
+            Source::Stdin => write!(f, "stdin"),
+            Source::File { path } => write!(f, "{}", path.display()),
+            Source::Packet { path, index } => write!(f, "{}#{}", path.display(), index),
+            Source::Archive { path, entry } => write!(f, "{}!/{}", path.display(), entry),
+        }
+    }
+}
+
+/// Where a match was found.
+///
+/// `start` and `end` are byte offsets into the source, `line` and `column` are 1-based
+/// with the column counted in characters.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub source: Source,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.source, self.line, self.column)
+    }
+}
+
+/// A piece of the content and the text it became.
+struct Segment {
+    text: Range<usize>,
+    content: Range<usize>,
+}
+
+/// A position in the content whose line and column are known: the start of the content,
+/// of each line and of each UTF-16 string, and one every [`MARK_INTERVAL`] bytes of long lines.
+struct Mark {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+/// Bytes between the marks of a line, so a column is counted from a mark close by.
+const MARK_INTERVAL: usize = 1 << 10;
+
+/// What the characters of the content are encoded in.
+#[derive(Clone, Copy)]
+enum Characters {
+    Utf8,
+    /// Only the ranges the text was decoded from, other bytes count as one character each.
+    Utf16 { big_endian: bool },
+}
+
+/// Text to identify, along with what's needed to map a match in it back to the original input.
+pub(crate) struct ScanInput<'a> {
+    source: Source,
+    content: &'a [u8],
+    pub text: String,
+    // the pieces of content the text was built from, in order
+    segments: Vec<Segment>,
+    characters: Characters,
+    // in order of their offset
+    marks: Vec<Mark>,
+    /// The text is made of lines identified on their own, patterns anchor to each line
+    /// instead of the whole text.
+    pub lines: bool,
+    // where `content` starts if it's only a part of the source
+    offset: usize,
+    lines_before: usize,
+    // characters of the first line before `content`, if it starts mid-line
+    columns_before: usize,
+}
+
+impl<'a> ScanInput<'a> {
+    pub fn from_text(text: &'a str) -> Self {
+        let segments = vec![Segment { text: 0..text.len(), content: 0..text.len() }];
+        let marks = utf8_marks(text.as_bytes());
+        Self::new(Source::Text, text.as_bytes(), text.to_string(), segments, Characters::Utf8, marks)
+    }
+
+    /// Joins the given ranges of `content` as lines,
+    /// dropping the `\r` of `\r\n` line breaks so patterns anchored to a line end match.
+    pub fn from_ranges(source: Source, content: &'a [u8], ranges: &[Range<usize>]) -> Self {
+        let mut text = String::new();
+        let mut segments = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            push_text(&mut text, &mut segments, &String::from_utf8_lossy(&content[range.clone()]),
+                range.start);
+        }
+        let marks = utf8_marks(content);
+        Self::new(source, content, text, segments, Characters::Utf8, marks).by_line()
+    }
+
+    /// Decodes the given ranges of `content` as UTF-16 and joins them as lines,
+    /// dropping the `\r` of `\r\n` line breaks like [`from_ranges`](Self::from_ranges).
+    pub fn from_utf16(
+        source: Source,
+        content: &'a [u8],
+        ranges: &[Range<usize>],
+        big_endian: bool,
+    ) -> Self {
+        let newline = if big_endian { [0, b'\n'] } else { [b'\n', 0] };
+        let mut text = String::new();
+        let mut segments = Vec::new();
+        // lines and columns are counted in characters of the strings,
+        // bytes between them count as one character each
+        let mut marks = vec![Mark { offset: 0, line: 0, column: 0 }];
+        let (mut line, mut column, mut position) = (0, 0, 0);
+        for range in ranges {
+            for &b in &content[position..range.start] {
+                if b == b'\n' {
+                    (line, column) = (line + 1, 0);
+                } else {
+                    column += 1;
+                }
+            }
+            marks.push(Mark { offset: range.start, line, column });
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            let units = content[range.clone()].chunks_exact(2).map(|unit| match big_endian {
+                true => u16::from_be_bytes([unit[0], unit[1]]),
+                false => u16::from_le_bytes([unit[0], unit[1]]),
+            });
+            position = range.start;
+            let mut marked = position;
+            for c in char::decode_utf16(units) {
+                let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+                let len = if c == char::REPLACEMENT_CHARACTER { 2 } else { c.len_utf16() * 2 };
+                let crlf = c == '\r' && content.get(position + 2..position + 4) == Some(&newline[..]);
+                if !crlf {
+                    segments.push(Segment {
+                        text: text.len()..text.len() + c.len_utf8(),
+                        content: position..position + len,
+                    });
+                    text.push(c);
+                }
+                position += len;
+                if c == '\n' {
+                    (line, column) = (line + 1, 0);
+                    marks.push(Mark { offset: position, line, column });
+                    marked = position;
+                } else {
+                    column += 1;
+                    if position - marked >= MARK_INTERVAL {
+                        marks.push(Mark { offset: position, line, column });
+                        marked = position;
+                    }
+                }
+            }
+            position = range.end;
+        }
+        Self::new(source, content, text, segments, Characters::Utf16 { big_endian }, marks)
+            .by_line()
+    }
+
+    /// `text` decoded from `content`, e.g. an escaped string, with `pieces` mapping ranges of
+    /// the text to the bytes of the content they were decoded from.
+    pub fn from_decoded(
+        source: Source,
+        content: &'a [u8],
+        text: String,
+        pieces: &[(Range<usize>, Range<usize>)],
+    ) -> Self {
+        let segments = pieces.iter()
+            .map(|(text, content)| Segment { text: text.clone(), content: content.clone() })
+            .collect();
+        let marks = utf8_marks(content);
+        Self::new(source, content, text, segments, Characters::Utf8, marks)
+    }
+
+    /// Decodes `content` as UTF-8, replacing invalid sequences with U+FFFD.
+    /// Like [`from_ranges`](Self::from_ranges), line breaks are normalized to `\n`.
+    pub fn from_lossy(source: Source, content: &'a [u8]) -> Self {
+        let mut text = String::with_capacity(content.len());
+        let mut segments = Vec::new();
+        let mut offset = 0;
+        for chunk in content.utf8_chunks() {
+            push_text(&mut text, &mut segments, chunk.valid(), offset);
+            offset += chunk.valid().len();
+            if !chunk.invalid().is_empty() {
+                let invalid = offset..offset + chunk.invalid().len();
+                let replacement = text.len()..text.len() + char::REPLACEMENT_CHARACTER.len_utf8();
+                segments.push(Segment { text: replacement, content: invalid });
+                text.push(char::REPLACEMENT_CHARACTER);
+                offset += chunk.invalid().len();
+            }
+        }
+        let marks = utf8_marks(content);
+        Self::new(source, content, text, segments, Characters::Utf8, marks)
+    }
+
+    fn new(
+        source: Source,
+        content: &'a [u8],
+        text: String,
+        segments: Vec<Segment>,
+        characters: Characters,
+        marks: Vec<Mark>,
+    ) -> Self {
+        ScanInput {
+            source, content, text, segments, characters, marks, lines: false, offset: 0,
+            lines_before: 0, columns_before: 0
+        }
+    }
+
+    /// Marks the text as lines identified on their own, like the lines of a text file.
+    pub fn by_line(mut self) -> Self {
+        self.lines = true;
+        self
+    }
+
+    /// Marks `content` as the part of the source starting at byte `offset`,
+    /// after `lines_before` complete lines.
+    pub fn starting_at(mut self, offset: usize, lines_before: usize) -> Self {
+        self.offset = offset;
+        self.lines_before = lines_before;
+        self
+    }
+
+    /// Marks `content` as starting `columns_before` characters into its first line.
+    pub fn continuing_line(mut self, columns_before: usize) -> Self {
+        self.columns_before = columns_before;
+        self
+    }
+
+    /// Byte offset of `content` in the source.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Location in the original input of `range` of the text.
+    pub fn locate(&self, range: Range<usize>) -> Location {
+        let start = self.content_offset(range.start, false);
+        let end = self.content_offset(range.end, true).max(start);
+        self.locate_content(start..end)
+    }
+
+    /// Location in the original input of `range` of the content.
+    pub fn locate_content(&self, range: Range<usize>) -> Location {
+        let Range { start, end } = range;
+        let mark = &self.marks[self.marks.partition_point(|m| m.offset <= start).max(1) - 1];
+        let between = &self.content[mark.offset.min(start)..start];
+        let mut column = mark.column + match self.characters {
+            Characters::Utf8 => utf8_chars(between),
+            // strings start with a mark, so `between` is a part of one
+            Characters::Utf16 { big_endian } => {
+                let units = between.chunks_exact(2).map(|unit| match big_endian {
+                    true => u16::from_be_bytes([unit[0], unit[1]]),
+                    false => u16::from_le_bytes([unit[0], unit[1]]),
+                });
+                char::decode_utf16(units).count()
+            }
+        };
+        let line = mark.line;
+        if line == 0 {
+            column += self.columns_before;
+        }
+
+        Location {
+            source: self.source.clone(),
+            start: self.offset + start,
+            end: self.offset + end,
+            line: self.lines_before + line + 1,
+            column: column + 1,
+        }
+    }
+
+    /// Offset in the content of `text_offset`, which is the end of a range if `end`,
+    /// so it belongs to the segment before if it's where two segments meet.
+    fn content_offset(&self, text_offset: usize, end: bool) -> usize {
+        let i = self.segments
+            .partition_point(|s| s.text.start < text_offset || (!end && s.text.start == text_offset))
+            .max(1) - 1;
+        let Some(segment) = self.segments.get(i) else {
+            return 0;
+        };
+        // Offsets in text that was added between segments, e.g. the joining newlines,
+        // belong to the end of the previous segment
+        if text_offset >= segment.text.end {
+            segment.content.end
+        } else {
+            let offset = segment.content.start + text_offset.saturating_sub(segment.text.start);
+            offset.min(segment.content.end)
+        }
+    }
+}
+
+/// Marks of UTF-8 `content`, with columns counted in characters.
+fn utf8_marks(content: &[u8]) -> Vec<Mark> {
+    let mut marks = vec![Mark { offset: 0, line: 0, column: 0 }];
+    let (mut line, mut column, mut marked) = (0, 0, 0);
+    for (i, &b) in content.iter().enumerate() {
+        if b == b'\n' {
+            (line, column) = (line + 1, 0);
+            marks.push(Mark { offset: i + 1, line, column });
+            marked = i + 1;
+        } else if is_char_start(b) {
+            if i - marked >= MARK_INTERVAL {
+                marks.push(Mark { offset: i, line, column });
+                marked = i;
+            }
+            column += 1;
+        }
+    }
+    marks
+}
+
+/// Characters in UTF-8 `bytes`, invalid sequences count as one character per byte
+/// that isn't a continuation byte.
+fn utf8_chars(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&b| is_char_start(b)).count()
+}
+
+fn is_char_start(b: u8) -> bool {
+    b & 0xc0 != 0x80
+}
+
+/// Appends `piece`, which starts at `content_offset` of the content, to `text`
+/// without the `\r` of its `\r\n` line breaks.
+fn push_text(text: &mut String, segments: &mut Vec<Segment>, piece: &str, content_offset: usize) {
+    let mut start = 0;
+    let mut push = |text: &mut String, piece: &str, start: usize| {
+        segments.push(Segment {
+            text: text.len()..text.len() + piece.len(),
+            content: content_offset + start..content_offset + start + piece.len(),
+        });
+        text.push_str(piece);
+    };
+    for (i, _) in piece.match_indices("\r\n") {
+        push(text, &piece[start..i], start);
+        start = i + 1;
+    }
+    push(text, &piece[start..], start);
+}
@@ -0,0 +1,242 @@
+use pcap_parser::Linktype;
+use serde::Serialize;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Transport protocol of a packet, or the IP protocol if it has no transport layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+    Icmp,
+    Icmpv6,
+    /// Any other protocol on top of IP.
+    Ip,
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Protocol::Tcp => write!(f, "tcp"),
+            Protocol::Udp => write!(f, "udp"),
+            Protocol::Icmp => write!(f, "icmp"),
+            Protocol::Icmpv6 => write!(f, "icmpv6"),
+            Protocol::Ip => write!(f, "ip"),
+        }
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = anyhow::Error;
+
+    fn from_str(protocol: &str) -> anyhow::Result<Self> {
+        match protocol.to_lowercase().as_str() {
+            "tcp" => Ok(Protocol::Tcp),
+            "udp" => Ok(Protocol::Udp),
+            "icmp" => Ok(Protocol::Icmp),
+            "icmpv6" => Ok(Protocol::Icmpv6),
+            "ip" => Ok(Protocol::Ip),
+            _ => anyhow::bail!("expected one of tcp, udp, icmp, icmpv6 or ip"),
+        }
+    }
+}
+
+/// The packet of a capture a match was found in.
+///
+/// Frames that can't be dissected down to IP are scanned as a whole
+/// and only have a number and timestamp.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Packet {
+    /// 1-based packet number, as shown by Wireshark.
+    pub number: usize,
+    /// Capture time in UTC with microseconds, e.g. `2023-11-14T22:13:20.000000Z`.
+    pub timestamp: Option<String>,
+    pub protocol: Option<Protocol>,
+    pub src: Option<IpAddr>,
+    pub dst: Option<IpAddr>,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+}
+
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.number)?;
+        if let Some(timestamp) = &self.timestamp {
+            write!(f, " {}", timestamp)?;
+        }
+        if let Some(protocol) = self.protocol {
+            write!(f, " {}", protocol)?;
+        }
+        let endpoint = |address: Option<IpAddr>, port: Option<u16>| match (address, port) {
+            (Some(IpAddr::V6(address)), Some(port)) => format!("[{}]:{}", address, port),
+            (Some(address), Some(port)) => format!("{}:{}", address, port),
+            (Some(address), None) => address.to_string(),
+            (None, _) => String::new(),
+        };
+        if self.src.is_some() {
+            write!(f, " {} -> {}",
+                endpoint(self.src, self.src_port), endpoint(self.dst, self.dst_port))?;
+        }
+        Ok(())
+    }
+}
+
+/// What the link, network and transport headers of a frame tell about it.
+pub(crate) struct Dissected {
+    /// Byte range of the payload inside the frame.
+    pub payload: Range<usize>,
+    pub protocol: Protocol,
+    pub src: IpAddr,
+    pub dst: IpAddr,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+    /// Header of TCP segments, `None` for other protocols and non-first IP fragments.
+    pub tcp: Option<TcpHeader>,
+}
+
+/// What reassembling a TCP stream needs from a segment's header.
+#[derive(Clone, Copy)]
+pub(crate) struct TcpHeader {
+    pub seq: u32,
+    pub syn: bool,
+    /// FIN or RST, the stream in this direction is over.
+    pub end: bool,
+}
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+/// Dissects an Ethernet, Linux cooked, loopback or raw IP `frame` down to its payload.
+///
+/// Returns `None` for other link types, protocols other than IPv4 and IPv6
+/// and truncated or malformed headers.
+pub(crate) fn dissect(linktype: Linktype, frame: &[u8]) -> Option<Dissected> {
+    let (ethertype, offset) = match linktype {
+        Linktype::ETHERNET => {
+            let mut offset = 12;
+            let mut ethertype = read_u16(frame, offset)?;
+            while matches!(ethertype, ETHERTYPE_VLAN | ETHERTYPE_QINQ) {
+                offset += 4;
+                ethertype = read_u16(frame, offset)?;
+            }
+            (Some(ethertype), offset + 2)
+        }
+        Linktype::LINUX_SLL => (Some(read_u16(frame, 14)?), 16),
+        Linktype::LINUX_SLL2 => (Some(read_u16(frame, 0)?), 20),
+        // 4 bytes of address family, whose values differ between systems
+        Linktype::NULL | Linktype::LOOP => (None, 4),
+        Linktype::RAW | Linktype::IPV4 | Linktype::IPV6 => (None, 0),
+        _ => return None,
+    };
+
+    let version = frame.get(offset)? >> 4;
+    match (ethertype, version) {
+        (Some(ETHERTYPE_IPV4) | None, 4) => dissect_ipv4(frame, offset),
+        (Some(ETHERTYPE_IPV6) | None, 6) => dissect_ipv6(frame, offset),
+        _ => None,
+    }
+}
+
+fn dissect_ipv4(frame: &[u8], offset: usize) -> Option<Dissected> {
+    let header = frame.get(offset..offset + 20)?;
+    let header_len = (header[0] & 0x0f) as usize * 4;
+    let total_len = read_u16(header, 2)? as usize;
+    if header_len < 20 || total_len < header_len {
+        return None;
+    }
+    // frames may be padded or cut off by the snapshot length
+    let end = frame.len().min(offset + total_len);
+    let src = IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(&header[12..16]).ok()?));
+    let dst = IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(&header[16..20]).ok()?));
+    // only the first fragment starts with the transport header
+    let first_fragment = read_u16(header, 6)? & 0x1fff == 0;
+    dissect_transport(frame, offset + header_len..end, header[9], first_fragment, src, dst)
+}
+
+fn dissect_ipv6(frame: &[u8], offset: usize) -> Option<Dissected> {
+    let header = frame.get(offset..offset + 40)?;
+    let end = frame.len().min(offset + 40 + read_u16(header, 4)? as usize);
+    let src = IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(&header[8..24]).ok()?));
+    let dst = IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(&header[24..40]).ok()?));
+
+    let mut next_header = header[6];
+    let mut start = offset + 40;
+    let mut first_fragment = true;
+    loop {
+        let extension = frame.get(start..end)?;
+        let extension_len = match next_header {
+            // hop-by-hop, routing and destination options
+            0 | 43 | 60 => (*extension.get(1)? as usize + 1) * 8,
+            44 => {
+                first_fragment = read_u16(extension, 2)? & 0xfff8 == 0;
+                8
+            }
+            // authentication header
+            51 => (*extension.get(1)? as usize + 2) * 4,
+            _ => break,
+        };
+        next_header = *extension.first()?;
+        start += extension_len;
+    }
+    dissect_transport(frame, start..end, next_header, first_fragment, src, dst)
+}
+
+fn dissect_transport(
+    frame: &[u8],
+    ip_payload: Range<usize>,
+    ip_protocol: u8,
+    first_fragment: bool,
+    src: IpAddr,
+    dst: IpAddr,
+) -> Option<Dissected> {
+    let segment = frame.get(ip_payload.clone())?;
+    let (protocol, header_len) = match ip_protocol {
+        6 if first_fragment => (Protocol::Tcp, (*segment.get(12)? >> 4) as usize * 4),
+        17 if first_fragment => (Protocol::Udp, 8),
+        6 => (Protocol::Tcp, 0),
+        17 => (Protocol::Udp, 0),
+        1 => (Protocol::Icmp, 0),
+        58 => (Protocol::Icmpv6, 0),
+        _ => (Protocol::Ip, 0),
+    };
+    if protocol == Protocol::Tcp && header_len > 0 && header_len < 20 {
+        return None;
+    }
+    let (src_port, dst_port) = if header_len > 0 {
+        (Some(read_u16(segment, 0)?), Some(read_u16(segment, 2)?))
+    } else {
+        (None, None)
+    };
+    let payload_start = ip_payload.start + header_len;
+    if payload_start > ip_payload.end {
+        return None;
+    }
+    let tcp = if protocol == Protocol::Tcp && header_len > 0 {
+        let flags = segment[13];
+        Some(TcpHeader {
+            seq: u32::from_be_bytes([segment[4], segment[5], segment[6], segment[7]]),
+            syn: flags & 0x02 != 0,
+            end: flags & 0x05 != 0,
+        })
+    } else {
+        None
+    };
+    Some(Dissected {
+        payload: payload_start..ip_payload.end,
+        protocol,
+        src,
+        dst,
+        src_port,
+        dst_port,
+        tcp,
+    })
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    bytes.get(offset..offset + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
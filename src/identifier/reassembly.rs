@@ -0,0 +1,377 @@
+use crate::identifier::packet::{Packet, TcpHeader};
+use crate::identifier::MAX_MATCH_LEN;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::IpAddr;
+use std::ops::Range;
+
+// Streams are identified in chunks of this size, so a long download doesn't pile up in memory
+const MAX_CHUNK_LEN: usize = 1 << 20;
+// End of a chunk repeated at the start of the next one, like the windows of files:
+// matches starting in its last `MAX_MATCH_LEN` bytes belong to the next chunk,
+// the bytes before are there for the patterns looking behind
+const CHUNK_OVERLAP: usize = 2 * MAX_MATCH_LEN;
+// Out-of-order data held back per stream before the missing segments are given up on
+const MAX_PENDING_LEN: usize = 1 << 20;
+// Streams held open at once, the least recently active one is flushed beyond that
+const MAX_STREAMS: usize = 1 << 12;
+// Data held back by all streams together, the least recently active ones are flushed beyond that
+const MAX_BUFFERED_LEN: usize = 64 << 20;
+// Streams remembered as ended, so their retransmissions don't open them again
+const MAX_ENDED_STREAMS: usize = 1 << 12;
+
+/// One direction of a TCP connection, `(source, source port, destination, destination port)`.
+type FlowKey = (IpAddr, u16, IpAddr, u16);
+
+/// A contiguous piece of a reassembled TCP stream, ready to be identified.
+pub(crate) struct StreamChunk {
+    pub data: Vec<u8>,
+    /// `(offset in data, offset of the payload in its frame, packet)` of each segment,
+    /// ordered by offset.
+    segments: Vec<(usize, usize, Packet)>,
+    /// Part of the data whose matches belong to this chunk,
+    /// the others start in data identified with the previous or next chunk.
+    pub owned: Range<usize>,
+}
+
+impl StreamChunk {
+    /// The packet `offset` of the data was captured in, along with the offset in its frame
+    /// and the offset in the data where the packet's segment starts.
+    pub fn locate(&self, offset: usize) -> (&Packet, usize, usize) {
+        let i = self.segments.partition_point(|&(start, _, _)| start <= offset).max(1) - 1;
+        let (start, frame_offset, packet) = &self.segments[i];
+        (packet, frame_offset + offset - start, *start)
+    }
+
+    /// The packet the chunk starts in.
+    pub fn first_packet(&self) -> &Packet {
+        &self.segments[0].2
+    }
+}
+
+/// A segment that arrived before the data preceding it.
+struct PendingSegment {
+    payload: Vec<u8>,
+    frame_offset: usize,
+    packet: Packet,
+}
+
+struct Stream {
+    /// Sequence number of the next byte of the stream.
+    next_seq: u32,
+    /// Position of `next_seq` in the stream, sequence numbers wrap around but this doesn't.
+    position: u64,
+    data: Vec<u8>,
+    segments: Vec<(usize, usize, Packet)>,
+    /// Bytes at the start of `data` whose matches belong to the previous chunk.
+    lead: usize,
+    /// Out-of-order segments by their position in the stream.
+    pending: BTreeMap<u64, PendingSegment>,
+    pending_len: usize,
+    /// Number of the packet last seen, the stream's key in `Reassembler::recency`.
+    last_packet: usize,
+    /// The start of the stream is known, either from its SYN or because data was identified.
+    /// Until then segments before the start move it back, as captures may begin mid-stream
+    /// with segments out of order.
+    anchored: bool,
+}
+
+impl Stream {
+    fn new(header: &TcpHeader) -> Self {
+        Stream {
+            // the SYN flag takes up a sequence number
+            next_seq: header.seq.wrapping_add(header.syn as u32),
+            position: 0,
+            data: Vec::new(),
+            segments: Vec::new(),
+            lead: 0,
+            pending: BTreeMap::new(),
+            pending_len: 0,
+            last_packet: 0,
+            anchored: header.syn,
+        }
+    }
+
+    /// Bytes of data held back by the stream.
+    fn buffered(&self) -> usize {
+        self.data.len() + self.pending_len
+    }
+
+    /// Position of `seq` in the stream, `None` if it comes before the stream's start.
+    fn position_of(&self, seq: u32) -> Option<u64> {
+        // sequence numbers up to 2 GiB behind `next_seq` are retransmissions
+        self.position.checked_add_signed(seq.wrapping_sub(self.next_seq) as i32 as i64)
+    }
+
+    /// Moves the start of an unanchored stream back to `seq`,
+    /// returns false if the stream is anchored or `seq` is too far back.
+    fn move_start(&mut self, seq: u32) -> bool {
+        let start_seq = self.next_seq.wrapping_sub(self.position as u32);
+        let shift = start_seq.wrapping_sub(seq) as u64;
+        if self.anchored || shift > MAX_PENDING_LEN as u64 {
+            return false;
+        }
+
+        // the data received so far is out of order now
+        let data = std::mem::take(&mut self.data);
+        let segments = std::mem::take(&mut self.segments);
+        let ends: Vec<usize> = segments.iter()
+            .skip(1)
+            .map(|&(start, _, _)| start)
+            .chain([data.len()])
+            .collect();
+        let mut pending: BTreeMap<u64, PendingSegment> = std::mem::take(&mut self.pending)
+            .into_iter()
+            .map(|(position, segment)| (position + shift, segment))
+            .collect();
+        for ((start, frame_offset, packet), end) in segments.into_iter().zip(ends) {
+            pending.insert(start as u64 + shift, PendingSegment {
+                payload: data[start..end].to_vec(),
+                frame_offset,
+                packet,
+            });
+        }
+        self.pending_len = pending.values().map(|segment| segment.payload.len()).sum();
+        self.pending = pending;
+        self.next_seq = seq;
+        self.position = 0;
+        true
+    }
+
+    /// Adds a segment's payload starting at `position`, queueing it if data before it is missing.
+    fn push(
+        &mut self,
+        position: u64,
+        payload: &[u8],
+        frame_offset: usize,
+        packet: &Packet,
+        ready: &mut Vec<StreamChunk>
+    ) {
+        if position > self.position {
+            if !self.pending.contains_key(&position) {
+                self.pending_len += payload.len();
+                self.pending.insert(position, PendingSegment {
+                    payload: payload.to_vec(),
+                    frame_offset,
+                    packet: packet.clone(),
+                });
+            }
+            if self.pending_len > MAX_PENDING_LEN {
+                self.skip_gap(ready);
+            }
+            return;
+        }
+
+        // drops what was received before, retransmissions and overlapping segments
+        let already_received = (self.position - position) as usize;
+        if already_received < payload.len() {
+            self.append(&payload[already_received..], frame_offset + already_received, packet, ready);
+            self.push_pending(ready);
+        }
+    }
+
+    fn append(
+        &mut self,
+        bytes: &[u8],
+        frame_offset: usize,
+        packet: &Packet,
+        ready: &mut Vec<StreamChunk>
+    ) {
+        self.segments.push((self.data.len(), frame_offset, packet.clone()));
+        self.data.extend_from_slice(bytes);
+        self.position += bytes.len() as u64;
+        self.next_seq = self.next_seq.wrapping_add(bytes.len() as u32);
+        if self.data.len() >= MAX_CHUNK_LEN {
+            self.flush(ready, false);
+        }
+    }
+
+    /// Adds the pending segments that are no longer preceded by missing data.
+    fn push_pending(&mut self, ready: &mut Vec<StreamChunk>) {
+        while let Some(entry) = self.pending.first_entry() {
+            if *entry.key() > self.position {
+                break;
+            }
+            let (position, segment) = entry.remove_entry();
+            self.pending_len -= segment.payload.len();
+            let already_received = (self.position - position) as usize;
+            if already_received < segment.payload.len() {
+                self.append(
+                    &segment.payload[already_received..],
+                    segment.frame_offset + already_received,
+                    &segment.packet,
+                    ready
+                );
+            }
+        }
+    }
+
+    /// Gives up on the data missing before the first pending segment.
+    /// The data before the gap becomes a chunk of its own, so no match spans the gap.
+    fn skip_gap(&mut self, ready: &mut Vec<StreamChunk>) {
+        let Some(&position) = self.pending.keys().next() else {
+            return;
+        };
+        self.flush(ready, true);
+        self.next_seq = self.next_seq.wrapping_add((position - self.position) as u32);
+        self.position = position;
+        self.push_pending(ready);
+    }
+
+    /// Moves the data into a chunk. Unless it's the `last` of the data before a gap
+    /// or the end of the stream, the end of the chunk is kept for the next one.
+    fn flush(&mut self, ready: &mut Vec<StreamChunk>, last: bool) {
+        if self.data.len() <= self.lead {
+            self.data.clear();
+            self.segments.clear();
+            self.lead = 0;
+            return;
+        }
+        let len = self.data.len();
+        let owned_end = if last { len } else { len.saturating_sub(MAX_MATCH_LEN).max(self.lead) };
+        let kept_from = if last {
+            len
+        } else {
+            // the kept data starts with a whole segment where it can, so lines and columns of
+            // matches in it are still counted from the start of the payload of their packet
+            let from = len.saturating_sub(CHUNK_OVERLAP);
+            let i = self.segments.partition_point(|&(start, _, _)| start <= from).max(1) - 1;
+            let segment_start = self.segments[i].0;
+            if from - segment_start <= MAX_MATCH_LEN { segment_start } else { from }
+        };
+        self.anchored = true;
+        let chunk = StreamChunk {
+            data: std::mem::take(&mut self.data),
+            segments: std::mem::take(&mut self.segments),
+            owned: self.lead..owned_end,
+        };
+
+        if kept_from < chunk.data.len() {
+            let (packet, frame_offset, _) = chunk.locate(kept_from);
+            self.segments.push((0, frame_offset, packet.clone()));
+            self.segments.extend(chunk.segments.iter()
+                .filter(|&&(start, _, _)| start > kept_from)
+                .map(|(start, frame_offset, packet)| (start - kept_from, *frame_offset, packet.clone())));
+            self.data.extend_from_slice(&chunk.data[kept_from..]);
+        }
+        self.lead = owned_end.saturating_sub(kept_from).min(self.data.len());
+        ready.push(chunk);
+    }
+
+    /// Flushes everything, skipping over the gaps before pending segments.
+    fn finish(&mut self, ready: &mut Vec<StreamChunk>) {
+        while !self.pending.is_empty() {
+            self.skip_gap(ready);
+        }
+        self.flush(ready, true);
+    }
+}
+
+/// Reassembles the TCP streams of a capture from their segments.
+///
+/// Segments are put in order by their sequence number, retransmitted and overlapping data
+/// is dropped. Each direction of a connection is a stream of its own.
+///
+/// Streams are flushed and dropped when they end with a FIN or RST. Beyond [`MAX_STREAMS`]
+/// open streams or [`MAX_BUFFERED_LEN`] bytes held back by all of them,
+/// the least recently active streams are flushed early.
+#[derive(Default)]
+pub(crate) struct Reassembler {
+    streams: HashMap<FlowKey, Stream>,
+    /// Keys of the open streams by the number of the packet last seen in them,
+    /// so the least recently active one comes first.
+    recency: BTreeMap<usize, FlowKey>,
+    /// Sum of the bytes the open streams hold back.
+    buffered: usize,
+    /// Streams that ended recently with the number of their last packet, oldest first.
+    ended: VecDeque<(FlowKey, usize)>,
+    ended_at: HashMap<FlowKey, usize>,
+    ready: Vec<StreamChunk>,
+}
+
+impl Reassembler {
+    /// Adds a TCP segment of `packet`, whose payload starts at `frame_offset` of its frame.
+    pub fn add(&mut self, packet: &Packet, header: &TcpHeader, payload: &[u8], frame_offset: usize) {
+        let (Some(src), Some(src_port), Some(dst), Some(dst_port)) =
+            (packet.src, packet.src_port, packet.dst, packet.dst_port) else {
+            return;
+        };
+        let key = (src, src_port, dst, dst_port);
+        // a SYN opens the connection again, anything else of an ended stream is a retransmission
+        if self.ended_at.contains_key(&key) {
+            if !header.syn {
+                return;
+            }
+            self.ended_at.remove(&key);
+        }
+
+        let mut stream = match self.streams.remove(&key) {
+            Some(stream) => {
+                self.recency.remove(&stream.last_packet);
+                self.buffered -= stream.buffered();
+                stream
+            }
+            // segments without data, like the ACKs after a FIN, don't open a stream
+            None if payload.is_empty() && !header.syn => return,
+            None => {
+                if self.streams.len() >= MAX_STREAMS {
+                    self.flush_least_recent();
+                }
+                Stream::new(header)
+            }
+        };
+        stream.last_packet = packet.number;
+        let seq = header.seq.wrapping_add(header.syn as u32);
+        match stream.position_of(seq) {
+            Some(position) => stream.push(position, payload, frame_offset, packet, &mut self.ready),
+            None if stream.move_start(seq) => {
+                stream.push(0, payload, frame_offset, packet, &mut self.ready)
+            }
+            None => {}
+        }
+
+        if header.end {
+            stream.finish(&mut self.ready);
+            self.ended_at.insert(key, packet.number);
+            self.ended.push_back((key, packet.number));
+            if self.ended.len() > MAX_ENDED_STREAMS {
+                let (oldest, number) = self.ended.pop_front().unwrap();
+                // unless the stream was opened again and ended later
+                if self.ended_at.get(&oldest) == Some(&number) {
+                    self.ended_at.remove(&oldest);
+                }
+            }
+            return;
+        }
+        self.buffered += stream.buffered();
+        self.recency.insert(stream.last_packet, key);
+        self.streams.insert(key, stream);
+        while self.buffered > MAX_BUFFERED_LEN && self.flush_least_recent() {}
+    }
+
+    /// Flushes and drops the least recently active stream, returns false if there is none.
+    fn flush_least_recent(&mut self) -> bool {
+        let Some((_, key)) = self.recency.pop_first() else {
+            return false;
+        };
+        if let Some(mut stream) = self.streams.remove(&key) {
+            self.buffered -= stream.buffered();
+            stream.finish(&mut self.ready);
+        }
+        true
+    }
+
+    /// Takes the chunks of reassembled data that are ready to be identified.
+    pub fn take_ready(&mut self) -> Vec<StreamChunk> {
+        std::mem::take(&mut self.ready)
+    }
+
+    /// Flushes all streams at the end of the capture, the ones that started first first.
+    pub fn finish(mut self) -> Vec<StreamChunk> {
+        let mut streams: Vec<Stream> = self.streams.into_values().collect();
+        streams.sort_by_key(|stream| stream.segments.first().map(|(_, _, p)| p.number));
+        for stream in &mut streams {
+            stream.finish(&mut self.ready);
+        }
+        self.ready
+    }
+}
@@ -0,0 +1,140 @@
+use crate::identifier::location::ScanInput;
+use crate::identifier::{Identifier, Match};
+use crate::regex_pd::PatternData;
+use once_cell::sync::Lazy;
+use regex::bytes::Regex;
+use serde::Deserialize;
+use std::borrow::Cow;
+
+/// Tag of the patterns matching file signatures.
+pub const FILE_SIGNATURE_TAG: &str = "File Signature";
+
+/// A file format recognized by the magic bytes at a fixed offset of its files.
+pub(crate) struct FileSignature {
+    pub data: PatternData,
+    bytes: Vec<u8>,
+    offset: usize,
+    /// Also look for the signature anywhere inside files,
+    /// only set for signatures unlikely to turn up by chance.
+    embedded: bool,
+}
+
+/// Embedded signatures shorter than this must have a non-printable byte,
+/// short printable ones turn up in any text mentioning the format.
+const MIN_PRINTABLE_EMBEDDED_LEN: usize = 8;
+
+/// An entry of `data/file_signatures.json`.
+#[derive(Deserialize)]
+struct SignatureEntry {
+    name: String,
+    /// Hex bytes separated by spaces.
+    signature: String,
+    offset: usize,
+    #[serde(default)]
+    embedded: bool,
+    /// Lower for short magic bytes that files of other formats often start with.
+    #[serde(default = "default_rarity")]
+    rarity: f32,
+    extension: String,
+    mime: String,
+}
+
+fn default_rarity() -> f32 {
+    1.0
+}
+
+pub(crate) static FILE_SIGNATURES: Lazy<Vec<FileSignature>> = Lazy::new(|| {
+    let entries: Vec<SignatureEntry> =
+        serde_json::from_str(include_str!("../../data/file_signatures.json")).unwrap();
+    entries.into_iter()
+        .map(|entry| {
+            let bytes: Vec<u8> = entry.signature.split_whitespace()
+                .map(|byte| u8::from_str_radix(byte, 16).unwrap())
+                .collect();
+            let embedded = entry.embedded && (bytes.len() >= MIN_PRINTABLE_EMBEDDED_LEN
+                || bytes.iter().any(|b| !(b' '..=b'~').contains(b)));
+            FileSignature {
+                data: PatternData {
+                    name: Cow::Owned(entry.name),
+                    plural_name: false,
+                    description: Some(Cow::Owned(format!(
+                        "File extension: .{}, MIME type: {}", entry.extension, entry.mime
+                    ))),
+                    exploit: None,
+                    rarity: entry.rarity,
+                    url: None,
+                    tags: Cow::Borrowed(&[Cow::Borrowed(FILE_SIGNATURE_TAG)]),
+                    uses_non_standard_regex: false,
+                    validator: None,
+                },
+                bytes,
+                offset: entry.offset,
+                embedded,
+            }
+        })
+        .collect()
+});
+
+// Finds all embedded signatures in one pass
+static EMBEDDED_SIGNATURES: Lazy<Regex> = Lazy::new(|| {
+    let alternatives: Vec<String> = FILE_SIGNATURES.iter()
+        .filter(|s| s.embedded)
+        .map(|s| s.bytes.iter().map(|b| format!(r"\x{:02X}", b)).collect())
+        .collect();
+    Regex::new(&format!("(?-u){}", alternatives.join("|"))).unwrap()
+});
+
+/// Matches of the file signatures in `content`, the one of the file's own format
+/// and those of files embedded in it, ordered by offset.
+/// The file's own format is only looked for if `content` is the start of the file.
+pub(crate) fn find_signatures(
+    identifier: &Identifier,
+    input: &ScanInput,
+    content: &[u8]
+) -> Vec<Match> {
+    // signatures at a fixed offset are only looked for at the start of the file
+    let at_file_start = input.offset() == 0;
+    let mut matches = if at_file_start {
+        find_format_signatures(identifier, input, content)
+    } else {
+        Vec::new()
+    };
+
+    for found in EMBEDDED_SIGNATURES.find_iter(content) {
+        // a signature at the start of the file is the file's own format
+        if at_file_start && found.start() == 0 {
+            continue;
+        }
+        let signature = FILE_SIGNATURES.iter()
+            .find(|s| s.embedded && s.bytes == found.as_bytes());
+        if let Some(signature) = signature.filter(|s| included(identifier, s)) {
+            matches.push(signature_match(signature, input, found.start()));
+        }
+    }
+
+    matches.sort_by_key(|m| m.location.start);
+    matches
+}
+
+/// Matches of the signatures of the file's own format, `content` is the start of the file.
+pub(crate) fn find_format_signatures(
+    identifier: &Identifier,
+    input: &ScanInput,
+    content: &[u8]
+) -> Vec<Match> {
+    FILE_SIGNATURES.iter()
+        .filter(|s| included(identifier, s))
+        .filter(|s| content.get(s.offset..s.offset + s.bytes.len()) == Some(s.bytes.as_slice()))
+        .map(|s| signature_match(s, input, s.offset))
+        .collect()
+}
+
+fn included(identifier: &Identifier, signature: &FileSignature) -> bool {
+    !identifier.filter.gets_excluded(&signature.data)
+}
+
+fn signature_match(signature: &FileSignature, input: &ScanInput, start: usize) -> Match {
+    let end = start + signature.bytes.len();
+    let matched_on = signature.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    Match::new(&signature.data, matched_on, input.locate_content(start..end))
+}
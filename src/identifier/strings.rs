@@ -0,0 +1,168 @@
+use rayon::prelude::*;
+use std::ops::Range;
+
+// Binary data is searched for strings in chunks of this size in parallel
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// Byte ranges of the strings in binary data, by their encoding.
+pub(crate) struct Strings {
+    /// ASCII and UTF-8 strings.
+    pub utf8: Vec<Range<usize>>,
+    pub utf16le: Vec<Range<usize>>,
+    pub utf16be: Vec<Range<usize>>,
+}
+
+/// Strings of at least `min_len` printable characters, spaces, tabs and line breaks in `bytes`,
+/// without the surrounding whitespace, like `strings` finds them.
+///
+/// UTF-16 strings are limited to Latin-1 characters, random data rarely looks like that.
+pub(crate) fn find_strings(bytes: &[u8], min_len: usize) -> Strings {
+    let utf8 = find_runs(bytes, utf8_char, |start| match start {
+        0 => 0,
+        // skip the rest of a character started in the chunk before
+        _ => bytes[start..].iter().take(3).take_while(|&&b| b & 0xc0 == 0x80).count(),
+    });
+    let utf8 = trim_runs(bytes, utf8, min_len, 1);
+
+    let utf16 = |big_endian: bool| {
+        let unit = move |bytes: &[u8], i: usize| -> (usize, bool) {
+            let Some(unit) = bytes.get(i..i + 2) else {
+                return (2, false);
+            };
+            let (high, low) = if big_endian { (unit[0], unit[1]) } else { (unit[1], unit[0]) };
+            (2, high == 0 && (is_printable_ascii(low) || low >= 0xa0))
+        };
+        // strings may start at odd offsets too
+        let mut runs: Vec<Range<usize>> = (0..2)
+            .flat_map(|alignment| find_runs(bytes, unit, |_| alignment))
+            .collect();
+        runs.sort_by_key(|run| run.start);
+        trim_runs(bytes, runs, min_len, 2)
+    };
+    let (utf16le, utf16be) = drop_shifted(utf16(false), utf16(true));
+
+    Strings { utf8, utf16le, utf16be }
+}
+
+fn is_printable_ascii(byte: u8) -> bool {
+    byte.is_ascii_graphic() || matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+/// Length of the character at `i` and whether it's printable.
+fn utf8_char(bytes: &[u8], i: usize) -> (usize, bool) {
+    let len = match bytes[i] {
+        byte @ 0..=0x7f => return (1, is_printable_ascii(byte)),
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => return (1, false),
+    };
+    match bytes.get(i..i + len).and_then(|b| std::str::from_utf8(b).ok()) {
+        Some(c) => (len, !c.starts_with(char::is_control)),
+        None => (1, false),
+    }
+}
+
+/// Runs of printable characters in `bytes`, as told by `char_at` from a position.
+/// `first` is where to start in a chunk starting at the given offset.
+fn find_runs(
+    bytes: &[u8],
+    char_at: impl Fn(&[u8], usize) -> (usize, bool) + Sync,
+    first: impl Fn(usize) -> usize + Sync,
+) -> Vec<Range<usize>> {
+    // Runs of each chunk, including the short ones at the chunk borders
+    // as they may continue in the neighbouring chunk
+    let chunk_runs = (0..bytes.len().div_ceil(CHUNK_SIZE))
+        .into_par_iter()
+        .map(|n| {
+            let end = bytes.len().min((n + 1) * CHUNK_SIZE);
+            let mut i = n * CHUNK_SIZE + first(n * CHUNK_SIZE);
+            let mut runs: Vec<Range<usize>> = Vec::new();
+            let mut start: Option<usize> = None;
+
+            // characters may go on past the end of the chunk
+            while i < end {
+                let (len, printable) = char_at(bytes, i);
+                match (printable, start) {
+                    (true, None) => start = Some(i),
+                    (false, Some(s)) => {
+                        runs.push(s..i);
+                        start = None;
+                    }
+                    _ => {}
+                }
+                i += len;
+            }
+            if let Some(s) = start {
+                runs.push(s..i.min(bytes.len()));
+            }
+            runs
+        })
+        .collect::<Vec<Vec<Range<usize>>>>();
+
+    // Stitch runs our chunk division divided back together
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for run in chunk_runs.into_iter().flatten() {
+        match ranges.last_mut() {
+            Some(last) if last.end == run.start => last.end = run.end,
+            _ => ranges.push(run),
+        }
+    }
+    ranges
+}
+
+/// Trims the whitespace around runs of `unit_len` byte code units
+/// and drops those shorter than `min_len` characters.
+fn trim_runs(
+    bytes: &[u8],
+    mut runs: Vec<Range<usize>>,
+    min_len: usize,
+    unit_len: usize,
+) -> Vec<Range<usize>> {
+    let is_whitespace = |unit: &[u8]| unit.iter().all(|&b| b == 0 || b.is_ascii_whitespace())
+        && unit.iter().any(|&b| b != 0);
+    for run in &mut runs {
+        while run.len() >= unit_len && is_whitespace(&bytes[run.start..run.start + unit_len]) {
+            run.start += unit_len;
+        }
+        while run.len() >= unit_len && is_whitespace(&bytes[run.end - unit_len..run.end]) {
+            run.end -= unit_len;
+        }
+    }
+    runs.retain(|run| {
+        let chars = match unit_len {
+            1 => bytes[run.clone()].iter().filter(|&&b| b & 0xc0 != 0x80).count(),
+            _ => run.len() / unit_len,
+        };
+        chars > 0 && chars >= min_len
+    });
+    runs
+}
+
+/// Drops the strings that are another string read with the other byte order one byte off,
+/// keeping the longer one, or the one starting first.
+fn drop_shifted(
+    le: Vec<Range<usize>>,
+    be: Vec<Range<usize>>,
+) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    let mut keep_le = vec![true; le.len()];
+    let mut keep_be = vec![true; be.len()];
+    let mut first = 0;
+    for (i, l) in le.iter().enumerate() {
+        while first < be.len() && be[first].end <= l.start {
+            first += 1;
+        }
+        for (j, b) in be.iter().enumerate().skip(first).take_while(|(_, b)| b.start < l.end) {
+            // the shifted string is at most as long and starts a byte later
+            if (b.len(), l.start) > (l.len(), b.start) {
+                keep_le[i] = false;
+            } else {
+                keep_be[j] = false;
+            }
+        }
+    }
+    let keep = |runs: Vec<Range<usize>>, keep: Vec<bool>| {
+        runs.into_iter().zip(keep).filter(|(_, keep)| *keep).map(|(run, _)| run).collect()
+    };
+    (keep(le, keep_le), keep(be, keep_be))
+}
@@ -0,0 +1,614 @@
+use crate::identifier::location::ScanInput;
+use crate::identifier::{Identifier, Match, MatchedTexts, Source, WINDOW_LEN};
+use saphyr_parser::Event;
+use std::fs;
+use std::io::{self, Cursor};
+use std::ops::Range;
+use std::path::Path;
+
+// Values of keys with these words in their name are likely secrets,
+// compared in lowercase without `_`, `-` and the like
+const SECRET_KEYS: [&str; 9] = [
+    "password", "passwd", "passphrase", "secret", "token", "apikey", "accesskey", "privatekey",
+    "credential",
+];
+// Added to the rarity of matches in the values of secret keys
+const SECRET_KEY_BOOST: f32 = 0.5;
+// Deeper JSON is scanned as plain text
+const MAX_JSON_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Yaml,
+    Toml,
+    Ini,
+    Dotenv,
+}
+
+impl Format {
+    /// The format of the file at `path` by its name.
+    fn detect(path: &Path) -> Option<Format> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        // .env, .env.local, .env.production
+        if name == ".env" || name.starts_with(".env.") {
+            return Some(Format::Dotenv);
+        }
+        match name.rsplit_once('.')?.1 {
+            "json" => Some(Format::Json),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "toml" => Some(Format::Toml),
+            "ini" | "cfg" | "properties" => Some(Format::Ini),
+            "env" => Some(Format::Dotenv),
+            _ => None,
+        }
+    }
+
+    /// The scalar values of `text` with their key paths, sorted by where they are.
+    /// `None` if `text` isn't in this format.
+    fn parse(self, text: &str) -> Option<Vec<Value>> {
+        let mut values = match self {
+            Format::Json => parse_json(text)?,
+            Format::Yaml => parse_yaml(text)?,
+            Format::Toml => parse_toml(text)?,
+            Format::Ini => parse_ini(text),
+            Format::Dotenv => parse_dotenv(text),
+        };
+        values.retain(|value| !value.text.is_empty());
+        values.sort_by_key(|value| value.range.start);
+        Some(values)
+    }
+}
+
+/// A scalar value of a structured file.
+struct Value {
+    /// Path of the keys leading to the value, e.g. `database.password` or `servers[0].host`.
+    key: String,
+    /// Byte range of the value in the file, as it's written.
+    range: Range<usize>,
+    /// The value as it reads, e.g. with its escape sequences replaced and without its quotes.
+    text: String,
+    /// Ranges of `text` and of the bytes of `range` they were read from,
+    /// relative to the start of each.
+    pieces: Vec<(Range<usize>, Range<usize>)>,
+}
+
+impl Value {
+    /// A value that reads as it's written at `range` of `file`.
+    fn raw(key: String, file: &str, range: Range<usize>) -> Value {
+        let text = file[range.clone()].to_string();
+        let pieces = vec![(0..text.len(), 0..text.len())];
+        Value { key, range, text, pieces }
+    }
+
+    /// A value that reads as `text` and is written at `range` of `file`.
+    fn read(key: String, file: &str, range: Range<usize>, text: String) -> Value {
+        if text == file[range.clone()] {
+            return Value::raw(key, file, range);
+        }
+        let pieces = align(&text, &file[range.clone()]);
+        Value { key, range, text, pieces }
+    }
+}
+
+/// Whether the file at `path` is a JSON, YAML, TOML, INI or dotenv file small enough
+/// to be parsed as a whole.
+pub(crate) fn is_structured(path: &Path) -> io::Result<bool> {
+    Ok(Format::detect(path).is_some() && fs::metadata(path)?.len() <= WINDOW_LEN as u64)
+}
+
+/// Identifies the values of the JSON, YAML, TOML, INI or dotenv file at `path` one by one,
+/// naming the key path of each match, then the rest of the file, like comments.
+///
+/// Matches in the values of keys named like secrets, e.g. `password` or `api_token`,
+/// have their rarity raised, those raised out of the rarity range of the filter are dropped.
+/// Values there are reported even if their pattern is too common otherwise, as long as it
+/// matches the whole value.
+/// Files that don't parse are identified like any other file.
+pub(crate) fn identify_structured(
+    identifier: &Identifier,
+    path: &Path,
+    matches: &mut Vec<Match>,
+) -> anyhow::Result<()> {
+    let content = fs::read(path)?;
+    let source = Source::File { path: path.to_path_buf() };
+    let values = match (Format::detect(path), std::str::from_utf8(&content)) {
+        (Some(format), Ok(text)) if !text.contains('\0') => format.parse(text),
+        _ => None,
+    };
+    let Some(values) = values else {
+        return identifier.identify_windows(source, Cursor::new(content), matches);
+    };
+
+    let secret_identifier = Identifier {
+        filter: identifier.filter.boosted(SECRET_KEY_BOOST),
+        ..identifier.clone()
+    };
+    let whole_file = ScanInput::from_lossy(source.clone(), &content).by_line();
+    let mut matched_texts = MatchedTexts::default();
+    for value in &values {
+        let secret = is_secret_key(&value.key);
+        let start = whole_file.locate_content(value.range.start..value.range.start);
+        let input = ScanInput::from_decoded(source.clone(), &content[value.range.clone()],
+            value.text.clone(), &value.pieces)
+            .starting_at(value.range.start, start.line - 1)
+            .continuing_line(start.column - 1);
+
+        let mut found = if secret { &secret_identifier } else { identifier }.find_all(&input);
+        // patterns too common otherwise only count if they make up the whole value
+        found.retain(|m| m.rarity >= identifier.filter.min || m.matched_on == value.text);
+        // and are dropped as duplicates of the patterns that aren't
+        found.sort_by_key(|m| m.rarity < identifier.filter.min);
+        for m in &mut found {
+            m.key_path = Some(value.key.clone());
+            if secret {
+                m.rarity = (m.rarity + SECRET_KEY_BOOST).min(1.0);
+            }
+        }
+        if secret {
+            let (min, max) = (identifier.filter.min, identifier.filter.max);
+            found.retain(|m| (min..=max).contains(&m.rarity));
+        }
+        identifier.add_matches(found, &mut matched_texts, matches);
+    }
+
+    // keys and comments, the matches overlapping values are found in them already
+    let in_value = |m: &Match| {
+        let i = values.partition_point(|value| value.range.end <= m.location.start);
+        values.get(i).is_some_and(|value| value.range.start < m.location.end)
+    };
+    let mut found = identifier.find_all(&whole_file);
+    found.retain(|m| !in_value(m));
+    identifier.add_matches(found, &mut matched_texts, matches);
+
+    Ok(())
+}
+
+/// Whether the last key of `path` is named like it holds a secret.
+fn is_secret_key(path: &str) -> bool {
+    let key = path.rsplit('.').next().unwrap_or(path);
+    let key = key.split('[').next().unwrap_or(key);
+    let key: String = key.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    SECRET_KEYS.iter().any(|secret| key.contains(secret))
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) }
+}
+
+fn index(path: &str, index: usize) -> String {
+    format!("{}[{}]", path, index)
+}
+
+/// Lines of `text` with the offset they start at, without their line break.
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut offset = 0;
+    text.split('\n').map(move |line| {
+        let start = offset;
+        offset += line.len() + 1;
+        (start, line.strip_suffix('\r').unwrap_or(line))
+    })
+}
+
+/// Offset of the quote closing a string in `text`, which starts after the opening one.
+/// Backslashes escape characters in double quoted strings.
+fn closing_quote(text: &str, quote: u8) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if quote == b'"' => i += 2,
+            b if b == quote => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Range of the scalar `text`, which starts at `start`, without its quotes or,
+/// if unquoted, a comment started by one of `comments` after whitespace.
+fn scalar(text: &str, start: usize, comments: &[char]) -> Option<Range<usize>> {
+    let trimmed = text.trim_start();
+    let start = start + text.len() - trimmed.len();
+    let trimmed = trimmed.trim_end();
+    if let Some(quote @ (b'"' | b'\'')) = trimmed.bytes().next() {
+        if let Some(end) = closing_quote(&trimmed[1..], quote) {
+            return Some(start + 1..start + 1 + end);
+        }
+    }
+    let end = trimmed.char_indices()
+        .find(|&(i, c)| comments.contains(&c) && (i == 0 || trimmed[..i].ends_with([' ', '\t'])))
+        .map_or(trimmed.len(), |(i, _)| i);
+    let value = trimmed[..end].trim_end();
+    (!value.is_empty()).then(|| start..start + value.len())
+}
+
+fn parse_json(text: &str) -> Option<Vec<Value>> {
+    let mut parser = JsonParser { text, position: 0, values: Vec::new() };
+    parser.value("", 0)?;
+    parser.skip_whitespace();
+    (parser.position == text.len()).then_some(parser.values)
+}
+
+struct JsonParser<'a> {
+    text: &'a str,
+    position: usize,
+    values: Vec<Value>,
+}
+
+impl JsonParser<'_> {
+    fn value(&mut self, key: &str, depth: usize) -> Option<()> {
+        if depth > MAX_JSON_DEPTH {
+            return None;
+        }
+        self.skip_whitespace();
+        match *self.text.as_bytes().get(self.position)? {
+            b'{' => {
+                self.position += 1;
+                self.skip_whitespace();
+                if self.eat(b'}') {
+                    return Some(());
+                }
+                loop {
+                    self.skip_whitespace();
+                    let (_, name) = self.string()?;
+                    let path = join(key, &name);
+                    self.skip_whitespace();
+                    self.eat(b':').then_some(())?;
+                    self.value(&path, depth + 1)?;
+                    self.skip_whitespace();
+                    if !self.eat(b',') {
+                        return self.eat(b'}').then_some(());
+                    }
+                }
+            }
+            b'[' => {
+                self.position += 1;
+                self.skip_whitespace();
+                if self.eat(b']') {
+                    return Some(());
+                }
+                for i in 0.. {
+                    self.value(&index(key, i), depth + 1)?;
+                    self.skip_whitespace();
+                    if !self.eat(b',') {
+                        break;
+                    }
+                }
+                self.eat(b']').then_some(())
+            }
+            b'"' => {
+                let (range, string) = self.string()?;
+                self.values.push(Value::read(key.to_string(), self.text, range, string));
+                Some(())
+            }
+            _ => {
+                let start = self.position;
+                while self.text.as_bytes().get(self.position)
+                    .is_some_and(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.')) {
+                    self.position += 1;
+                }
+                match &self.text[start..self.position] {
+                    "" => return None,
+                    "true" | "false" | "null" => {}
+                    _ => self.values.push(Value::raw(key.to_string(), self.text, start..self.position)),
+                }
+                Some(())
+            }
+        }
+    }
+
+    /// The range of the string at the current position, without its quotes, and the string
+    /// it reads as.
+    fn string(&mut self) -> Option<(Range<usize>, String)> {
+        if !self.eat(b'"') {
+            return None;
+        }
+        let start = self.position;
+        loop {
+            match *self.text.as_bytes().get(self.position)? {
+                b'\\' => self.position += 2,
+                b'"' => {
+                    self.position += 1;
+                    break;
+                }
+                _ => self.position += 1,
+            }
+        }
+        let string = serde_json::from_str(&self.text[start - 1..self.position]).ok()?;
+        Some((start..self.position - 1, string))
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let eaten = self.text.as_bytes().get(self.position) == Some(&byte);
+        if eaten {
+            self.position += 1;
+        }
+        eaten
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.text.as_bytes().get(self.position).is_some_and(u8::is_ascii_whitespace) {
+            self.position += 1;
+        }
+    }
+}
+
+/// Scalars of the documents in `text`, with the keys of mappings as their key path.
+/// `None` if `text` isn't YAML.
+fn parse_yaml(text: &str) -> Option<Vec<Value>> {
+    enum Collection {
+        /// `key` names the value coming next, `None` while its key is.
+        Mapping { path: String, key: Option<String> },
+        Sequence { path: String, items: usize },
+    }
+
+    /// Key path of the node coming next in the innermost collection.
+    fn next_path(collections: &mut [Collection]) -> String {
+        match collections.last_mut() {
+            None => String::new(),
+            Some(Collection::Mapping { path, key }) => match key.take() {
+                Some(key) => join(path, &key),
+                // a collection or alias as a key, e.g. `? [a, b]`
+                None => {
+                    *key = Some("?".to_string());
+                    join(path, "?")
+                }
+            },
+            Some(Collection::Sequence { path, items }) => {
+                *items += 1;
+                index(path, *items - 1)
+            }
+        }
+    }
+
+    let mut values = Vec::new();
+    let mut collections: Vec<Collection> = Vec::new();
+    let mut offsets = CharOffsets { text, index: 0, offset: 0 };
+    for event in saphyr_parser::Parser::new_from_str(text) {
+        let (event, span) = event.ok()?;
+        if let (Event::Scalar(name, ..), Some(Collection::Mapping { key: key @ None, .. })) =
+            (&event, collections.last_mut()) {
+            *key = Some(name.to_string());
+            continue;
+        }
+        match event {
+            Event::Scalar(scalar, ..) => {
+                let path = next_path(&mut collections);
+                let range = offsets.offset(span.start.index())..offsets.offset(span.end.index());
+                values.push(Value::read(path, text, range, scalar.into_owned()));
+            }
+            Event::Alias(_) => {
+                next_path(&mut collections);
+            }
+            Event::MappingStart(..) => {
+                let path = next_path(&mut collections);
+                collections.push(Collection::Mapping { path, key: None });
+            }
+            Event::SequenceStart(..) => {
+                let path = next_path(&mut collections);
+                collections.push(Collection::Sequence { path, items: 0 });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                collections.pop();
+            }
+            _ => {}
+        }
+    }
+    Some(values)
+}
+
+/// Byte offsets of the characters of `text`, looked up mostly in increasing order.
+struct CharOffsets<'a> {
+    text: &'a str,
+    // the character looked up last and its offset
+    index: usize,
+    offset: usize,
+}
+
+impl CharOffsets<'_> {
+    fn offset(&mut self, index: usize) -> usize {
+        if index < self.index {
+            (self.index, self.offset) = (0, 0);
+        }
+        let rest = &self.text[self.offset..];
+        self.offset += rest.char_indices().nth(index - self.index).map_or(rest.len(), |(i, _)| i);
+        self.index = index;
+        self.offset
+    }
+}
+
+/// Values of the tables, arrays and inline tables of `text`. `None` if `text` isn't TOML.
+fn parse_toml(text: &str) -> Option<Vec<Value>> {
+    let document = toml_edit::ImDocument::parse(text).ok()?;
+    let mut values = Vec::new();
+    toml_item(text, document.as_item(), "", &mut values);
+    Some(values)
+}
+
+fn toml_item(text: &str, item: &toml_edit::Item, path: &str, values: &mut Vec<Value>) {
+    match item {
+        toml_edit::Item::Value(value) => toml_value(text, value, path, values),
+        toml_edit::Item::Table(table) => {
+            for (key, item) in table.iter() {
+                toml_item(text, item, &join(path, key), values);
+            }
+        }
+        toml_edit::Item::ArrayOfTables(tables) => {
+            for (i, table) in tables.iter().enumerate() {
+                for (key, item) in table.iter() {
+                    toml_item(text, item, &join(&index(path, i), key), values);
+                }
+            }
+        }
+        toml_edit::Item::None => {}
+    }
+}
+
+fn toml_value(text: &str, value: &toml_edit::Value, path: &str, values: &mut Vec<Value>) {
+    match value {
+        toml_edit::Value::String(string) => {
+            if let Some(range) = string.span() {
+                values.push(Value::read(path.to_string(), text, range, string.value().clone()));
+            }
+        }
+        toml_edit::Value::Array(array) => {
+            for (i, value) in array.iter().enumerate() {
+                toml_value(text, value, &index(path, i), values);
+            }
+        }
+        toml_edit::Value::InlineTable(table) => {
+            for (key, value) in table.iter() {
+                toml_value(text, value, &join(path, key), values);
+            }
+        }
+        toml_edit::Value::Boolean(_) => {}
+        // numbers and dates
+        _ => values.extend(value.span().map(|range| Value::raw(path.to_string(), text, range))),
+    }
+}
+
+/// Maps the characters of `text` to the bytes of `raw`, the scalar it was read from:
+/// to the same character or the escape sequence writing it. Quotes, whitespace and backslashes
+/// in between, like indentation or folded line breaks, are skipped.
+/// Characters not found, like the line breaks a block scalar keeps, map to where `raw` was at.
+fn align(text: &str, raw: &str) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut pieces: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    let mut position = 0;
+    for (i, c) in text.char_indices() {
+        let mut at = position;
+        let mut source = position..position;
+        while let Some(r) = raw[at..].chars().next() {
+            let escaped = if r == '\\' { escape(&raw[at..]) } else { None };
+            if let Some((_, len)) = escaped.filter(|&(escaped, _)| escaped == c) {
+                source = at..at + len;
+                break;
+            }
+            if r == c {
+                source = at..at + r.len_utf8();
+                break;
+            }
+            if !(r.is_whitespace() || matches!(r, '"' | '\'' | '\\')) {
+                break;
+            }
+            at += r.len_utf8();
+        }
+        position = source.end;
+        let piece = i..i + c.len_utf8();
+        // characters written as they read make up one piece
+        match pieces.last_mut() {
+            Some((text, raw)) if text.len() == raw.len() && piece.len() == source.len()
+                && text.end == piece.start && raw.end == source.start => {
+                text.end = piece.end;
+                raw.end = source.end;
+            }
+            _ => pieces.push((piece, source)),
+        }
+    }
+    pieces
+}
+
+/// The character written by the escape sequence `raw` starts with and its length,
+/// for the escape sequences of JSON, TOML and double quoted YAML strings.
+fn escape(raw: &str) -> Option<(char, usize)> {
+    let hex = |at: usize, len: usize| -> Option<u32> {
+        raw.get(at..at + len)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+    };
+    let c = match *raw.as_bytes().get(1)? {
+        b'n' => '\n',
+        b't' => '\t',
+        b'r' => '\r',
+        b'b' => '\u{8}',
+        b'f' => '\u{c}',
+        b'0' => '\0',
+        b'a' => '\u{7}',
+        b'v' => '\u{b}',
+        b'e' => '\u{1b}',
+        b'N' => '\u{85}',
+        b'_' => '\u{a0}',
+        b'L' => '\u{2028}',
+        b'P' => '\u{2029}',
+        b'x' => return Some((char::from_u32(hex(2, 2)?)?, 4)),
+        b'u' => {
+            let unit = hex(2, 4)?;
+            if !(0xd800..0xdc00).contains(&unit) {
+                return Some((char::from_u32(unit)?, 6));
+            }
+            // a surrogate pair, as JSON writes characters outside the BMP
+            let low = raw.get(6..8).filter(|&u| u == "\\u").and_then(|_| hex(8, 4))?;
+            let low = low.checked_sub(0xdc00).filter(|&low| low < 0x400)?;
+            return Some((char::from_u32(0x10000 + ((unit - 0xd800) << 10) + low)?, 12));
+        }
+        b'U' => return Some((char::from_u32(hex(2, 8)?)?, 10)),
+        b @ (b'"' | b'\'' | b'\\' | b'/' | b' ') => b as char,
+        _ => return None,
+    };
+    Some((c, 2))
+}
+
+/// Values of `key = value` and `key: value` lines, named `section.key` after their section.
+fn parse_ini(text: &str) -> Vec<Value> {
+    let mut values = Vec::new();
+    let mut section = String::new();
+    for (start, line) in lines(text) {
+        let content = line.trim();
+        if content.is_empty() || content.starts_with([';', '#']) {
+            continue;
+        }
+        if let Some(header) = content.strip_prefix('[').and_then(|c| c.strip_suffix(']')) {
+            section = header.trim().to_string();
+            continue;
+        }
+        let Some(separator) = line.find(['=', ':']) else {
+            continue;
+        };
+        let key = line[..separator].trim();
+        if !key.is_empty() {
+            let key = join(&section, key);
+            values.extend(scalar(&line[separator + 1..], start + separator + 1, &[';', '#'])
+                .map(|range| Value::raw(key, text, range)));
+        }
+    }
+    values
+}
+
+/// Values of `KEY=value` lines, which may start with `export`,
+/// with double quoted values spanning several lines.
+fn parse_dotenv(text: &str) -> Vec<Value> {
+    let mut values = Vec::new();
+    let mut skip_until = 0;
+    for (start, line) in lines(text) {
+        let content = line.trim_start();
+        if start < skip_until || content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let Some(equals) = line.find('=') else {
+            continue;
+        };
+        let key = line[..equals].trim();
+        let key = key.strip_prefix("export ").map_or(key, str::trim_start);
+        if key.is_empty() || !key.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-')) {
+            continue;
+        }
+        let key = key.to_string();
+        let value = &line[equals + 1..];
+        let trimmed = value.trim_start();
+        let value_start = start + equals + 1 + value.len() - trimmed.len();
+        if trimmed.starts_with('"') && closing_quote(&trimmed[1..], b'"').is_none() {
+            if let Some(end) = closing_quote(&text[value_start + 1..], b'"') {
+                let range = value_start + 1..value_start + 1 + end;
+                skip_until = range.end;
+                values.push(Value::raw(key, text, range));
+            }
+        } else {
+            values.extend(scalar(value, start + equals + 1, &['#'])
+                .map(|range| Value::raw(key, text, range)));
+        }
+    }
+    values
+}
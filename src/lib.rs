@@ -0,0 +1,36 @@
+//! Identify what something is! A pyWhat reimplementation in Rust.
+//!
+//! `what-rs` matches text, files, directories and packet captures against a database of
+//! known patterns (wallet addresses, API keys, URLs, ...) and reports what it found.
+//!
+//! ```
+//! use what_rs::{Filter, Identifier, Match};
+//!
+//! let identifier = Identifier::default()
+//!     .filter(Filter::default().include("ethereum").unwrap());
+//!
+//! let mut matches: Vec<Match> = Vec::new();
+//! identifier.identify_text("0x52908400098527886E0F7030069857D2E4169EE7", &mut matches).unwrap();
+//!
+//! assert_eq!(matches[0].name, "Ethereum (ETH) Wallet Address");
+//! ```
+
+mod regex_pd;
+pub mod details;
+pub mod export;
+pub mod filter;
+pub mod format;
+pub mod hash;
+pub mod identifier;
+pub mod options;
+pub mod sorter;
+pub mod validator;
+
+pub use crate::details::Details;
+pub use crate::filter::{FailOn, Filter};
+pub use crate::hash::{identify_hash, HashCandidate};
+pub use crate::identifier::{Encoding, Identifier, Location, Match, Packet, Protocol, Source};
+pub use crate::options::Options;
+pub use crate::regex_pd::{patterns, tags, CustomPatterns, PatternData, PATTERN_DATA};
+pub use crate::sorter::Sorter;
+pub use crate::validator::Validator;
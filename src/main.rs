@@ -1,23 +1,27 @@
-mod regex_pd;
-mod filter;
-mod identifier;
-mod format;
-mod sorter;
-mod options;
 mod cli;
 
 use crate::cli::{cli, generate_completions};
-use crate::filter::Filter;
-use crate::format::{get_format, output, OutputFormat};
-use crate::identifier::{identify, Match};
-use crate::regex_pd::TAGS;
-use crate::sorter::Sorter;
-
-use crate::options::Options;
+use what_rs::export::export_capture;
+use what_rs::format::{get_format, output};
+use what_rs::options::parse_filesize;
+use what_rs::{
+    tags, CustomPatterns, FailOn, Filter, Identifier, Match, Options, Sorter, Source
+};
+
+use anyhow::Context;
+use clap::ArgMatches;
 use colored::Colorize;
 use human_panic::setup_panic;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
 use std::process;
 
+// Exit status, see `--help`
+const EXIT_NO_MATCHES: i32 = 0;
+const EXIT_MATCHES: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+
 fn main() {
     setup_panic!();
 
@@ -28,23 +32,46 @@ fn main() {
     let cli_matches = cli().get_matches();
     if let Some(generator) = cli_matches.get_one::<String>("generate") {
         generate_completions(generator);
+        process::exit(0);
+    }
+
+    let mut patterns = CustomPatterns::default();
+    if let Some(pattern_files) = cli_matches.get_many::<String>("patterns") {
+        for pattern_file in pattern_files {
+            or_exit(patterns.load(Path::new(pattern_file)));
+        }
     }
 
     if cli_matches.get_flag("tags") {
-        print_tags();
+        print_tags(&patterns);
         process::exit(0);
     }
 
-    let mut pcapng = false;
+    let mut capture = false;
+    let mut export: Option<(PathBuf, bool)> = None;
 
-    let input = match cli_matches.subcommand() {
-        Some(("pcapng", sub_matches)) => {
-            pcapng = true;
-            sub_matches.get_one::<String>("input").cloned()
+    let (inputs, listed_paths) = match cli_matches.subcommand() {
+        Some(("pcap" | "pcapng", sub_matches)) => {
+            capture = true;
+            export = sub_matches.get_one::<String>("export-pcap")
+                .map(|path| (PathBuf::from(path), sub_matches.get_flag("export-flows")));
+            (sub_matches.get_many::<String>("input").unwrap().cloned().collect(), Vec::new())
         }
         _ => {
-            let input = cli_matches.get_one::<String>("input").cloned();
-            if input.is_none() {
+            let inputs: Vec<String> = cli_matches.get_many::<String>("input")
+                .map(|inputs| inputs.cloned().collect())
+                .unwrap_or_default();
+            let listed_paths = match cli_matches.get_one::<String>("files-from") {
+                Some(list) => {
+                    if list == "-" && inputs.iter().any(|i| i == "-") {
+                        eprintln!("The standard input can't be both an input and the --files-from list.");
+                        process::exit(EXIT_ERROR);
+                    }
+                    or_exit(read_path_list(list, cli_matches.get_flag("null-input")))
+                }
+                None => Vec::new(),
+            };
+            if inputs.is_empty() && !cli_matches.contains_id("files-from") {
                 if cli_matches.args_present() {
                     cli().help_template("{usage-heading} {usage}\n\n{all-args}{after-help}")
                         .print_help().unwrap();
@@ -55,52 +82,151 @@ fn main() {
                     println!("For more information see {}", env!("CARGO_PKG_HOMEPAGE"));
                     eprintln!("\nRun '--help' for usage.");
                 }
-                process::exit(1);
+                process::exit(EXIT_ERROR);
             }
-            input
+            (inputs, listed_paths)
         }
     };
 
-    let filter = Filter::default()
-        .rarity(cli_matches.get_one::<String>("rarity").unwrap())
-        .borderless(!cli_matches.get_flag("disable-borderless"))
-        .include(cli_matches.get_one::<String>("include").unwrap_or(&String::from("")))
-        .exclude(cli_matches.get_one::<String>("exclude").unwrap_or(&String::from("")));
+    let filter = or_exit(build_filter(&cli_matches));
+    let fail_on: Vec<FailOn> = cli_matches.get_many::<String>("fail-on")
+        .map(|conditions| conditions.map(|c| or_exit(FailOn::parse(c, &patterns))).collect())
+        .unwrap_or_default();
 
-
-
-    let mut options: Options = Options {
-        format: OutputFormat::DEFAULT,
+    let options: Options = Options {
+        format: or_exit(get_format(cli_matches.get_one::<String>("format").map(String::as_str))),
         verbose: cli_matches.get_flag("verbose"),
         only_text: cli_matches.get_flag("only_text"),
         allow_duplicates: cli_matches.get_flag("allow-duplicates"),
-        pcapng,
+        capture,
+        separator: cli_matches.get_one::<String>("separator").unwrap().clone(),
+        null_terminated: cli_matches.get_flag("null"),
+        no_ignore: cli_matches.get_flag("no-ignore"),
+        hidden: cli_matches.get_flag("hidden"),
+        max_depth: cli_matches.get_one::<usize>("max-depth").copied(),
+        follow_symlinks: cli_matches.get_flag("follow"),
+        max_filesize: cli_matches.get_one::<String>("max-filesize")
+            .map(|size| or_exit(parse_filesize(size))),
+        globs: cli_matches.get_many::<String>("glob")
+            .map(|globs| globs.cloned().collect())
+            .unwrap_or_default(),
+        exclude_globs: cli_matches.get_many::<String>("exclude-glob")
+            .map(|globs| globs.cloned().collect())
+            .unwrap_or_default(),
+        decode_depth: if cli_matches.get_flag("decode") {
+            *cli_matches.get_one::<usize>("decode-depth").unwrap()
+        } else {
+            0
+        },
+        min_string_len: *cli_matches.get_one::<usize>("min-length").unwrap(),
+        archive_depth: *cli_matches.get_one::<usize>("archive-depth").unwrap(),
+        max_decompressed: or_exit(parse_filesize(
+            cli_matches.get_one::<String>("max-decompressed").unwrap()
+        )),
+        max_compression_ratio: *cli_matches.get_one::<u64>("max-ratio").unwrap(),
     };
 
-    options.format = get_format(&cli_matches.get_one::<String>("format"));
+    if let Some(&threads) = cli_matches.get_one::<usize>("threads") {
+        or_exit(rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(anyhow::Error::from));
+    }
+
+    let identifier = Identifier::default()
+        .patterns(patterns)
+        .filter(filter)
+        .options(options.clone());
+    let mut matches: Vec<Match> = Vec::new();
+    for input in &inputs {
+        if input == "-" {
+            or_exit(identifier.identify_reader(Source::Stdin, io::stdin().lock(), &mut matches));
+        } else {
+            or_exit(identifier.identify(input, &mut matches));
+        }
+    }
+    for path in &listed_paths {
+        or_exit(identifier.identify_path(path, &mut matches));
+    }
 
-    if let Some(input) = input {
-        let mut matches: Vec<Match> = Vec::new();
-        identify(&input, &mut matches, &filter, &options).unwrap();
-        Sorter::default()
-            .key(cli_matches.get_one::<String>("key").unwrap())
-            .reverse(cli_matches.get_flag("reverse"))
-            .sort(&mut matches);
-        output(&matches, &options);
+    Sorter::default()
+        .key(cli_matches.get_one::<String>("key").unwrap())
+        .reverse(cli_matches.get_flag("reverse"))
+        .sort(&mut matches);
+    output(&matches, &options, &identifier.pattern_data());
+    if options.verbose {
+        println!("Found {} matches.", matches.len());
+    }
+    if let Some((path, whole_flows)) = &export {
+        let packets = or_exit(export_capture(&matches, path, *whole_flows));
         if options.verbose {
-            println!("Found {} matches.", matches.len());
+            println!("Exported {} packets to {}.", packets, path.display());
         }
+    }
+
+    let failed = if fail_on.is_empty() {
+        !matches.is_empty()
     } else {
-        eprintln!("Input as text or file/directory path expected. Run '--help' for usage.");
-        process::exit(1);
+        matches.iter().any(|m| fail_on.iter().any(|condition| condition.is_met_by(m)))
+    };
+    process::exit(if failed { EXIT_MATCHES } else { EXIT_NO_MATCHES });
+}
+
+/// Prints the error and exits if `result` is one.
+fn or_exit<T>(result: anyhow::Result<T>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{e:#}");
+        process::exit(EXIT_ERROR);
+    })
+}
+
+/// Reads the paths listed in `list`, or in the standard input if `list` is '-'.
+fn read_path_list(list: &str, null_separated: bool) -> anyhow::Result<Vec<PathBuf>> {
+    let mut content = Vec::new();
+    if list == "-" {
+        io::stdin().lock().read_to_end(&mut content)?;
+    } else {
+        content = fs::read(list).with_context(|| format!("Failed to read path list {:?}", list))?;
     }
+
+    let separator = if null_separated { b'\0' } else { b'\n' };
+    Ok(content
+        .split(|&b| b == separator)
+        .map(|path| if null_separated { path } else { path.strip_suffix(b"\r").unwrap_or(path) })
+        .filter(|path| !path.is_empty())
+        .map(path_from_bytes)
+        .collect())
 }
 
+/// Path of the raw bytes of a file name, which on Unix don't have to be UTF-8.
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
 
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn build_filter(cli_matches: &ArgMatches) -> anyhow::Result<Filter> {
+    Filter::default()
+        .rarity(cli_matches.get_one::<String>("rarity").unwrap())?
+        .borderless(!cli_matches.get_flag("disable-borderless"))
+        .include(cli_matches.get_one::<String>("include").map_or("", String::as_str))?
+        .exclude(cli_matches.get_one::<String>("exclude").map_or("", String::as_str))?
+        .validated_only(cli_matches.get_flag("validated-only"))
+        .protocols(cli_matches.get_one::<String>("protocol").unwrap())?
+        .ports(cli_matches.get_one::<String>("port").unwrap())?
+        .hosts(cli_matches.get_one::<String>("host").unwrap())
+}
 
-fn print_tags() {
+fn print_tags(patterns: &CustomPatterns) {
+    let mut tags = tags();
+    tags.extend(patterns.tags());
     println!("{}\n", "Available Tags:".purple());
-    println!("{}", TAGS
+    println!("{}", tags
         .iter()
         .map(String::as_str)
         .collect::<Vec<&str>>()
@@ -1,9 +1,82 @@
 use crate::format::OutputFormat;
 
+/// Settings that change how input is read and how results are reported.
+#[derive(Debug, Clone)]
 pub struct Options {
     pub format: OutputFormat,
     pub verbose: bool,
     pub only_text: bool,
     pub allow_duplicates: bool,
-    pub pcapng: bool,
-}
\ No newline at end of file
+    /// Only accept pcap and pcapng captures as files.
+    pub capture: bool,
+    /// Field separator of the raw output format.
+    pub separator: String,
+    /// Terminate raw output records with NUL instead of a newline.
+    pub null_terminated: bool,
+    /// Scan files excluded by `.gitignore`, `.ignore` and similar files in directories.
+    pub no_ignore: bool,
+    /// Scan hidden files and directories in directories.
+    pub hidden: bool,
+    /// Only scan files up to this many directories deep, the directory itself has depth 0.
+    pub max_depth: Option<usize>,
+    pub follow_symlinks: bool,
+    /// Skip files in directories larger than this many bytes.
+    pub max_filesize: Option<u64>,
+    /// Only scan files in directories matching one of these globs.
+    pub globs: Vec<String>,
+    /// Skip files and directories matching one of these globs.
+    pub exclude_globs: Vec<String>,
+    /// Decode base64, hex and percent-encoded text up to this many times and identify the
+    /// decoded text too, `0` doesn't decode.
+    pub decode_depth: usize,
+    /// Shortest printable string extracted from binary files.
+    pub min_string_len: usize,
+    /// Look into archives and compressed files nested up to this deep, `0` scans them as they are.
+    pub archive_depth: usize,
+    /// Stop decompressing an archive after this many bytes, counting all files in it.
+    pub max_decompressed: u64,
+    /// Skip files in archives that decompress to more than this many times their compressed size.
+    pub max_compression_ratio: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            format: OutputFormat::DEFAULT,
+            verbose: false,
+            only_text: false,
+            allow_duplicates: false,
+            capture: false,
+            separator: String::from(":"),
+            null_terminated: false,
+            no_ignore: false,
+            hidden: false,
+            max_depth: None,
+            follow_symlinks: false,
+            max_filesize: None,
+            globs: Vec::new(),
+            exclude_globs: Vec::new(),
+            decode_depth: 0,
+            min_string_len: 4,
+            archive_depth: 4,
+            max_decompressed: 1 << 30,
+            max_compression_ratio: 200,
+        }
+    }
+}
+
+/// Parses a size in bytes with an optional `K`, `M` or `G` suffix, e.g. `10M`.
+pub fn parse_filesize(size: &str) -> anyhow::Result<u64> {
+    let size = size.trim();
+    let (number, multiplier) = match size.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&size[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&size[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&size[..i], 1 << 30),
+        _ => (size, 1),
+    };
+    let number = number.parse::<u64>()
+        .map_err(|_| anyhow::anyhow!("Invalid file size {:?}. \
+        Size must be a number of bytes, optionally followed by K, M or G.", size))?;
+    number.checked_mul(multiplier)
+        .ok_or_else(|| anyhow::anyhow!("File size {:?} is too large.", size))
+}
@@ -1,18 +1,29 @@
+use std::borrow::Cow;
 use std::collections::HashSet;
-use serde::Serialize;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
 use regex::Regex;
 use once_cell::sync::Lazy;
+use crate::identifier::signature::FILE_SIGNATURES;
+use crate::validator::Validator;
 
+/// Metadata of a pattern used for identification.
 #[derive(Serialize, Debug, Clone)]
-pub(crate) struct PatternData {
-    pub name: &'static str,
+pub struct PatternData {
+    pub name: Cow<'static, str>,
     pub plural_name: bool,
-    pub description: Option<&'static str>,
-    pub exploit: Option<&'static str>,
+    pub description: Option<Cow<'static, str>>,
+    pub exploit: Option<Cow<'static, str>>,
     pub rarity: f32,
-    pub url: Option<&'static str>,
-    pub tags: &'static [&'static str],
-    // pub uses_non_standard_regex: bool, TODO: use it
+    pub url: Option<Cow<'static, str>>,
+    pub tags: Cow<'static, [Cow<'static, str>]>,
+    /// The pattern needs lookaround or backreferences and runs on fancy-regex.
+    pub uses_non_standard_regex: bool,
+    /// Checksum that tells real matches of the pattern from look-alikes.
+    pub validator: Option<Validator>,
     // pub children: Option<ChildrenData>,
 }
 
@@ -27,19 +38,217 @@ pub struct ChildrenData {
 }
  */
 
+/// Backtracking steps a fancy-regex pattern may take on one input before it gives up,
+/// so a pathological input can't hang a scan.
+pub const DEFAULT_BACKTRACK_LIMIT: usize = 100_000;
+
+/// Compiled regex of a pattern. Patterns the regex crate doesn't support run on fancy-regex.
+#[derive(Debug, Clone)]
+pub(crate) enum PatternRegex {
+    Standard(Regex),
+    Fancy(fancy_regex::Regex),
+}
+
+impl PatternRegex {
+    pub fn standard(regex: &str) -> anyhow::Result<PatternRegex> {
+        Ok(PatternRegex::Standard(Regex::new(regex)?))
+    }
+
+    pub fn fancy(regex: &str, backtrack_limit: usize) -> anyhow::Result<PatternRegex> {
+        let regex = fancy_regex::RegexBuilder::new(regex)
+            .backtrack_limit(backtrack_limit)
+            .build()?;
+        Ok(PatternRegex::Fancy(regex))
+    }
+
+    /// Byte ranges of all non-overlapping matches in `text`.
+    ///
+    /// A fancy-regex pattern stops at the first match attempt that exceeds its backtrack limit.
+    pub fn find_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        match self {
+            PatternRegex::Standard(re) => re.find_iter(text).map(|m| m.range()).collect(),
+            PatternRegex::Fancy(re) => re.find_iter(text)
+                .map_while(Result::ok)
+                .map(|m| m.range())
+                .collect(),
+        }
+    }
+}
+
 const _1: [PatternData; 0] = []; // so rust-analyzer won't complain about unused imports
-// this is `pub const PATTERN_DATA: [PatternData; 129] = ...`
+// this is `pub static PATTERN_DATA: [PatternData; 142] = ...`
 include!(concat!(env!("OUT_DIR"), "/pattern_data.rs"));
 
-const _2: [Lazy<Regex>; 0] = []; // so rust-analyzer won't complain about unused imports
-// this is `pub static REGEX: [Lazy<Regex>; 129] = ...`
-// this is `pub static REGEX_NO_ANCHOR: [Lazy<Regex>; 129] = ...`
+#[allow(clippy::declare_interior_mutable_const)]
+const _2: [Lazy<PatternRegex>; 0] = []; // so rust-analyzer won't complain about unused imports
+// this is `pub static REGEX: [Lazy<PatternRegex>; 142] = ...`
+// this is `pub static REGEX_LINES: [Lazy<PatternRegex>; 142] = ...`
+// this is `pub static REGEX_NO_ANCHOR: [Lazy<PatternRegex>; 142] = ...`
 include!(concat!(env!("OUT_DIR"), "/regex_data.rs"));
 
-pub(crate) static TAGS: Lazy<HashSet<String>> = Lazy::new(|| {
+/// A pattern loaded at runtime, compiled once on load.
+#[derive(Debug, Clone)]
+pub(crate) struct CustomPattern {
+    pub data: PatternData,
+    pub regex: PatternRegex,
+    /// `regex` with `^` and `$` matching at line breaks too.
+    pub regex_lines: PatternRegex,
+    pub regex_no_anchor: PatternRegex,
+}
+
+/// Patterns loaded at runtime, used by an [`Identifier`](crate::Identifier) in addition to
+/// the compiled-in [`PATTERN_DATA`].
+///
+/// ```
+/// use what_rs::{CustomPatterns, Identifier};
+///
+/// let mut patterns = CustomPatterns::default();
+/// patterns.load_str(r#"[{ "name": "Acme Token", "regex": "^acme_[a-f0-9]{16}$", "rarity": 1 }]"#)?;
+/// let identifier = Identifier::default().patterns(patterns);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CustomPatterns {
+    patterns: Vec<CustomPattern>,
+}
+
+/// An entry of a pattern database, same schema as `data/regex.json`.
+#[derive(Deserialize)]
+struct PatternEntry {
+    name: String,
+    regex: String,
+    #[serde(default)]
+    plural_name: bool,
+    description: Option<String>,
+    exploit: Option<String>,
+    rarity: f32,
+    url: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    backtrack_limit: Option<usize>,
+    validator: Option<Validator>,
+}
+
+// Same expressions build.rs uses to derive `REGEX_NO_ANCHOR` from `REGEX`
+static ANCHOR_START: Lazy<fancy_regex::Regex> = Lazy::new(|| {
+    fancy_regex::Regex::new(r"(?<!\\)\^(?![^\[\]]*(?<!\\)\])").unwrap()
+});
+static ANCHOR_END: Lazy<fancy_regex::Regex> = Lazy::new(|| {
+    fancy_regex::Regex::new(r"(?<!\\)\$(?![^\[\]]*(?<!\\)\])").unwrap()
+});
+
+fn remove_anchors(regex: &str) -> String {
+    let regex = ANCHOR_START.replace(regex, "");
+    ANCHOR_END.replace(&regex, "").to_string()
+}
+
+impl CustomPatterns {
+    /// Loads a JSON pattern database from `path` and adds it to the patterns.
+    /// Returns the number of patterns loaded.
+    pub fn load(&mut self, path: &Path) -> anyhow::Result<usize> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read pattern file {:?}", path))?;
+        self.load_str(&json)
+            .with_context(|| format!("Failed to load pattern file {:?}", path))
+    }
+
+    /// Same as [`load`](Self::load), but reads the pattern database from a JSON string.
+    ///
+    /// Either all patterns are loaded or, if one of them is invalid, none.
+    pub fn load_str(&mut self, json: &str) -> anyhow::Result<usize> {
+        let entries: Vec<PatternEntry> = serde_json::from_str(json)?;
+        let mut names: HashSet<String> = patterns().into_iter()
+            .chain(self.data().cloned())
+            .map(|p| p.name.into_owned())
+            .collect();
+
+        let mut loaded: Vec<CustomPattern> = Vec::with_capacity(entries.len());
+        for entry in entries {
+            if entry.name.is_empty() {
+                anyhow::bail!("Pattern name must not be empty");
+            }
+            if !names.insert(entry.name.clone()) {
+                anyhow::bail!("Pattern {:?} is already defined", entry.name);
+            }
+            if !(0f32..=1f32).contains(&entry.rarity) {
+                anyhow::bail!("Pattern {:?} has invalid rarity {}. \
+                Rarity must be between 0 and 1 inclusive.", entry.name, entry.rarity);
+            }
+            let regex_no_anchor = remove_anchors(&entry.regex);
+            let uses_non_standard_regex =
+                Regex::new(&entry.regex).is_err() || Regex::new(&regex_no_anchor).is_err();
+            let compile = |regex: &str| if uses_non_standard_regex {
+                let backtrack_limit = entry.backtrack_limit.unwrap_or(DEFAULT_BACKTRACK_LIMIT);
+                PatternRegex::fancy(regex, backtrack_limit)
+            } else {
+                PatternRegex::standard(regex)
+            };
+            let regex = compile(&entry.regex)
+                .with_context(|| format!("Pattern {:?} has an invalid regex", entry.name))?;
+            let regex_lines = compile(&format!("(?m){}", entry.regex))
+                .with_context(|| format!("Pattern {:?} has an invalid regex", entry.name))?;
+            let regex_no_anchor = compile(&regex_no_anchor)
+                .with_context(|| format!("Pattern {:?} has an invalid regex", entry.name))?;
+
+            loaded.push(CustomPattern {
+                data: PatternData {
+                    name: Cow::Owned(entry.name),
+                    plural_name: entry.plural_name,
+                    description: entry.description.map(Cow::Owned),
+                    exploit: entry.exploit.map(Cow::Owned),
+                    rarity: entry.rarity,
+                    url: entry.url.map(Cow::Owned),
+                    tags: entry.tags.into_iter().map(Cow::Owned).collect(),
+                    uses_non_standard_regex,
+                    validator: entry.validator,
+                },
+                regex,
+                regex_lines,
+                regex_no_anchor,
+            });
+        }
+
+        let count = loaded.len();
+        self.patterns.extend(loaded);
+        Ok(count)
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Metadata of the loaded patterns, in load order.
+    pub fn data(&self) -> impl Iterator<Item = &PatternData> {
+        self.patterns.iter().map(|p| &p.data)
+    }
+
+    /// All tags used by the loaded patterns, lowercased.
+    pub fn tags(&self) -> HashSet<String> {
+        self.data().flat_map(|p| p.tags.iter().map(|tag| tag.to_lowercase())).collect()
+    }
+
+    pub(crate) fn as_slice(&self) -> &[CustomPattern] {
+        &self.patterns
+    }
+}
+
+/// All compiled-in patterns used for identification,
+/// see [`Identifier::pattern_data`](crate::Identifier::pattern_data) for those of an identifier.
+pub fn patterns() -> Vec<PatternData> {
+    let mut patterns = PATTERN_DATA.to_vec();
+    patterns.extend(FILE_SIGNATURES.iter().map(|s| s.data.clone()));
+    patterns
+}
+
+/// All tags used by the compiled-in patterns, lowercased.
+pub fn tags() -> HashSet<String> {
     let mut tag_set = HashSet::new();
-    for pattern in PATTERN_DATA.iter() {
-        tag_set.extend(pattern.tags.iter().map(|&tag| tag.to_lowercase()));
+    for pattern in patterns().iter() {
+        tag_set.extend(pattern.tags.iter().map(|tag| tag.to_lowercase()));
     }
     tag_set
-});
\ No newline at end of file
+}
@@ -19,8 +19,8 @@ impl Sorter {
         self
     }
 
-    pub fn key(mut self, key: &String) -> Self {
-        self.key = match key.as_str() {
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = match key {
             "name" => SortKey::Name,
             "rarity" => SortKey::Rarity,
             "matched" => SortKey::Matched,
@@ -32,7 +32,7 @@ impl Sorter {
         self
     }
 
-    pub fn sort(self, matches: &mut Vec<Match>) -> Self {
+    pub fn sort(self, matches: &mut [Match]) -> Self {
 
         match self.key {
             SortKey::Name => {
@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+
+/// Checksum a pattern's matches carry, verified to tell real matches from look-alikes.
+///
+/// Named in snake case in pattern databases, e.g. `"validator": "luhn"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Validator {
+    /// Luhn check digit of payment card numbers, spaces are ignored.
+    Luhn,
+    /// Double SHA-256 checksum of Base58 encoded addresses.
+    Base58check,
+    /// Bech32 or Bech32m checksum of SegWit addresses.
+    Bech32,
+    /// Base58Check for legacy and Bech32 for SegWit Bitcoin addresses.
+    Base58checkOrBech32,
+    /// Checksum of Bitcoin Cash addresses, the `bitcoincash:` prefix is assumed if missing.
+    CashAddr,
+    /// Mixed-case checksum of Ethereum addresses.
+    /// All lowercase and all uppercase addresses carry no checksum.
+    Eip55,
+    /// Check digits of the Turkish identification number (T.C. Kimlik No).
+    TurkishIdentificationNumber,
+    /// Check digit of the Turkish tax number (Vergi Kimlik No).
+    TurkishTaxNumber,
+    /// Check character of the Italian fiscal code (Codice Fiscale).
+    ItalianFiscalCode,
+}
+
+impl Validator {
+    /// Whether the checksum of `text` is valid, `None` if `text` is written in a form
+    /// without a checksum, like an all lowercase Ethereum address.
+    pub fn validate(self, text: &str) -> Option<bool> {
+        let valid = match self {
+            Validator::Luhn => luhn(text),
+            Validator::Base58check => base58check(text),
+            Validator::Bech32 => bech32(text),
+            Validator::Base58checkOrBech32 => {
+                let lowercase = text.to_lowercase();
+                if lowercase.starts_with("bc1") || lowercase.starts_with("tb1") {
+                    bech32(text)
+                } else {
+                    base58check(text)
+                }
+            }
+            Validator::CashAddr => cash_addr(text),
+            Validator::Eip55 => return eip55(text),
+            Validator::TurkishIdentificationNumber => turkish_identification_number(text),
+            Validator::TurkishTaxNumber => turkish_tax_number(text),
+            Validator::ItalianFiscalCode => italian_fiscal_code(text),
+        };
+        Some(valid)
+    }
+}
+
+/// Digits of `text`, `None` if it contains anything but digits and whitespace.
+fn digits(text: &str) -> Option<Vec<u32>> {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_digit(10))
+        .collect()
+}
+
+fn luhn(text: &str) -> bool {
+    let Some(digits) = digits(text) else {
+        return false;
+    };
+    let sum: u32 = digits.iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (0, _) => d,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    digits.len() > 1 && sum.is_multiple_of(10)
+}
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+fn base58_decode(text: &str) -> Option<Vec<u8>> {
+    // little endian while decoding
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in text.bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|&a| a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    // every leading '1' is a leading zero byte
+    let zeros = text.bytes().take_while(|&c| c == b'1').count();
+    bytes.extend(std::iter::repeat_n(0, zeros));
+    bytes.reverse();
+    Some(bytes)
+}
+
+fn base58check(text: &str) -> bool {
+    let Some(bytes) = base58_decode(text) else {
+        return false;
+    };
+    if bytes.len() < 5 {
+        return false;
+    }
+    let (payload, checksum) = bytes.split_at(bytes.len() - 4);
+    let hash = Sha256::digest(Sha256::digest(payload));
+    hash[..4] == *checksum
+}
+
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
+/// Values of the Bech32 characters of `text`, `None` if one isn't a Bech32 character.
+fn bech32_values(text: &str) -> Option<Vec<u8>> {
+    text.bytes()
+        .map(|c| BECH32_CHARSET.iter().position(|&b| b == c).map(|v| v as u8))
+        .collect()
+}
+
+/// Both Bech32 and CashAddr only allow all lowercase or all uppercase.
+fn single_case(text: &str) -> Option<String> {
+    let lower = text.chars().any(|c| c.is_ascii_lowercase());
+    let upper = text.chars().any(|c| c.is_ascii_uppercase());
+    (!(lower && upper)).then(|| text.to_ascii_lowercase())
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum: u32 = 1;
+    for &value in values {
+        let top = checksum >> 25;
+        checksum = (checksum & 0x1ffffff) << 5 ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn bech32(text: &str) -> bool {
+    let Some(text) = single_case(text) else {
+        return false;
+    };
+    let Some((hrp, data)) = text.rsplit_once('1') else {
+        return false;
+    };
+    let Some(data) = bech32_values(data) else {
+        return false;
+    };
+    if hrp.is_empty() || data.len() < 6 {
+        return false;
+    }
+
+    let mut values: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|c| c & 0x1f));
+    values.extend(data);
+    matches!(bech32_polymod(&values), BECH32_CONST | BECH32M_CONST)
+}
+
+fn cash_addr_polymod(values: &[u8]) -> u64 {
+    const GENERATOR: [u64; 5] = [0x98f2bc8e61, 0x79b76d99e2, 0xf33e5fb3c4, 0xae2eabe2a8, 0x1e4f43e470];
+    let mut checksum: u64 = 1;
+    for &value in values {
+        let top = checksum >> 35;
+        checksum = (checksum & 0x07ffffffff) << 5 ^ value as u64;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum ^ 1
+}
+
+fn cash_addr(text: &str) -> bool {
+    let Some(text) = single_case(text) else {
+        return false;
+    };
+    let (prefix, payload) = text.split_once(':').unwrap_or(("bitcoincash", &text));
+    let Some(payload) = bech32_values(payload) else {
+        return false;
+    };
+
+    let mut values: Vec<u8> = prefix.bytes().map(|c| c & 0x1f).collect();
+    values.push(0);
+    values.extend(payload);
+    cash_addr_polymod(&values) == 0
+}
+
+fn eip55(text: &str) -> Option<bool> {
+    let Some(address) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) else {
+        return Some(false);
+    };
+    if address.len() != 40 || !address.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some(false);
+    }
+    // a single case is how addresses are written without a checksum
+    if !address.chars().any(|c| c.is_ascii_uppercase())
+        || !address.chars().any(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+    // letters are uppercase where the nibble of the hash at their position is 8 or more
+    let hash = Keccak256::digest(address.to_ascii_lowercase().as_bytes());
+    Some(address.chars().enumerate().all(|(i, c)| {
+        let nibble = if i % 2 == 0 { hash[i / 2] >> 4 } else { hash[i / 2] & 0xf };
+        !c.is_ascii_alphabetic() || c.is_ascii_uppercase() == (nibble >= 8)
+    }))
+}
+
+fn turkish_identification_number(text: &str) -> bool {
+    let Some(d) = digits(text) else {
+        return false;
+    };
+    if d.len() != 11 || d[0] == 0 {
+        return false;
+    }
+    let odd = d[0] + d[2] + d[4] + d[6] + d[8];
+    let even = d[1] + d[3] + d[5] + d[7];
+    // (odd * 7 - even) mod 10, without going negative
+    let tenth = (odd * 7 + even * 9) % 10;
+    let eleventh = d[..10].iter().sum::<u32>() % 10;
+    d[9] == tenth && d[10] == eleventh
+}
+
+fn turkish_tax_number(text: &str) -> bool {
+    let Some(d) = digits(text) else {
+        return false;
+    };
+    if d.len() != 10 {
+        return false;
+    }
+    let sum: u32 = d[..9].iter()
+        .enumerate()
+        .map(|(i, &digit)| {
+            let shifted = (digit + 9 - i as u32) % 10;
+            if shifted == 9 {
+                9
+            } else {
+                shifted * (1 << (9 - i)) % 9
+            }
+        })
+        .sum();
+    d[9] == (10 - sum % 10) % 10
+}
+
+fn italian_fiscal_code(text: &str) -> bool {
+    // values of the characters at odd positions, indexed by digit or letter
+    const ODD_VALUES: [u32; 26] = [
+        1, 0, 5, 7, 9, 13, 15, 17, 19, 21, 2, 4, 18, 20, 11, 3, 6, 8, 12, 14, 16, 10, 22, 25, 24, 23
+    ];
+
+    let code = text.to_ascii_uppercase();
+    let bytes = code.as_bytes();
+    if bytes.len() != 16 || !bytes.iter().all(u8::is_ascii_alphanumeric) {
+        return false;
+    }
+    let sum: u32 = bytes[..15].iter()
+        .enumerate()
+        .map(|(i, &c)| {
+            let value = if c.is_ascii_digit() { c - b'0' } else { c - b'A' } as usize;
+            // positions are counted from 1, so even indices are odd positions
+            if i % 2 == 0 { ODD_VALUES[value] } else { value as u32 }
+        })
+        .sum();
+    bytes[15] == b'A' + (sum % 26) as u8
+}
@@ -0,0 +1,452 @@
+use assert_fs::prelude::*;
+use what_rs::{
+    identify_hash, CustomPatterns, Details, Encoding, Filter, Identifier, Match,
+    Options, Packet, Protocol, Source, Validator
+};
+
+#[test]
+fn identify_text_with_default_identifier() {
+    let mut matches: Vec<Match> = Vec::new();
+    Identifier::default().identify_text("0x52908400098527886E0F7030069857D2E4169EE7", &mut matches).unwrap();
+
+    assert!(matches.iter().any(|m| m.name == "Ethereum (ETH) Wallet Address"));
+}
+
+#[test]
+fn identify_text_respects_filter() {
+    let identifier = Identifier::default()
+        .filter(Filter::default().exclude("url").unwrap());
+    let mut matches: Vec<Match> = Vec::new();
+    identifier.identify_text("test.com", &mut matches).unwrap();
+
+    assert!(!matches.iter().any(|m| m.name == "Uniform Resource Locator (URL)"));
+}
+
+#[test]
+fn identify_file_by_path() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.txt")?;
+    file.write_str("0x52908400098527886E0F7030069857D2E4169EE7")?;
+
+    let mut matches: Vec<Match> = Vec::new();
+    Identifier::default().identify(file.path().to_str().unwrap(), &mut matches)?;
+    assert!(matches.iter().any(|m| m.name == "Ethereum (ETH) Wallet Address"));
+
+    let mut matches: Vec<Match> = Vec::new();
+    Identifier::default()
+        .options(Options { only_text: true, ..Options::default() })
+        .identify(file.path().to_str().unwrap(), &mut matches)?;
+    assert!(!matches.iter().any(|m| m.name == "Ethereum (ETH) Wallet Address"));
+
+    Ok(())
+}
+
+#[test]
+fn filter_rejects_unknown_tags() {
+    let identifier = Identifier::default()
+        .filter(Filter::default().include("not-a-tag").unwrap());
+    let error = identifier.identify_text("test.com", &mut Vec::new()).unwrap_err();
+    assert_eq!(error.to_string(), r#"Invalid tags: ["not-a-tag"]"#);
+    assert!(Filter::default().rarity("2:3").is_err());
+}
+
+#[test]
+fn custom_pattern_with_lookaround() {
+    let mut patterns = CustomPatterns::default();
+    patterns.load_str(r#"[
+        {
+            "name": "Lookaround Test Token",
+            "regex": "^(?<=key=)tok_(?!test)[a-z]{8}$",
+            "rarity": 1,
+            "tags": ["Lookaround Test"],
+            "backtrack_limit": 1000
+        }
+    ]"#).unwrap();
+
+    let identifier = Identifier::default()
+        .filter(Filter::default().include("lookaround test").unwrap())
+        .patterns(patterns.clone());
+    let mut matches: Vec<Match> = Vec::new();
+    identifier.identify_text("key=tok_abcdefgh key=tok_testtest", &mut matches).unwrap();
+
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].matched_on, "tok_abcdefgh");
+    assert!(identifier.pattern_data().iter()
+        .any(|p| p.name == "Lookaround Test Token" && p.uses_non_standard_regex));
+
+    let mut matches: Vec<Match> = Vec::new();
+    Identifier::default().identify_text("key=tok_abcdefgh", &mut matches).unwrap();
+    assert!(!matches.iter().any(|m| m.name == "Lookaround Test Token"));
+
+    // custom tags are known whether the patterns are set before or after the filter
+    let identifier = Identifier::default()
+        .patterns(patterns)
+        .filter(Filter::default().exclude("lookaround test").unwrap());
+    let mut matches: Vec<Match> = Vec::new();
+    identifier.identify_text("key=tok_abcdefgh", &mut matches).unwrap();
+    assert!(!matches.iter().any(|m| m.name == "Lookaround Test Token"));
+}
+
+#[test]
+fn locate_match_in_binary_file() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("sample.bin")?;
+    let mut content: Vec<u8> = b"first line\n\x00\x01\x02\xff".to_vec();
+    content.extend_from_slice(b"key 0x52908400098527886E0F7030069857D2E4169EE7\x00tail");
+    file.write_binary(&content)?;
+
+    let identifier = Identifier::default()
+        .filter(Filter::default().include("ethereum").unwrap());
+    let mut matches: Vec<Match> = Vec::new();
+    identifier.identify_file(file.path(), &mut matches)?;
+
+    assert_eq!(matches.len(), 1);
+    let location = &matches[0].location;
+    assert_eq!(location.source, Source::File { path: file.path().to_path_buf() });
+    assert_eq!((location.start, location.end), (19, 61));
+    assert_eq!(&content[location.start..location.end], matches[0].matched_on.as_bytes());
+    assert_eq!((location.line, location.column), (2, 9));
+
+    Ok(())
+}
+
+#[test]
+fn validate_checksums() {
+    let cases = [
+        (Validator::Luhn, "4607 0000 0000 0009", Some(true)),
+        (Validator::Luhn, "4607 0000 0000 0008", Some(false)),
+        (Validator::Base58checkOrBech32, "3EmUH8Uh9EXE7axgyAeBsCc2vdUdKkDqWK", Some(true)),
+        (Validator::Base58checkOrBech32, "3EmUH8Uh9EXE7axgyAeBsCc2vdUdKkDqWk", Some(false)),
+        (Validator::Base58checkOrBech32, "bc1qj89046x7zv6pm4n00qgqp505nvljnfp6xfznyw", Some(true)),
+        (Validator::Base58checkOrBech32, "bc1qj89046x7zv6pm4n00qgqp505nvljnfp6xfznyq", Some(false)),
+        (Validator::Base58check, "DANHz6EQVoWyZ9rER56DwTXHWUxfkv9k2o", Some(true)),
+        (Validator::CashAddr, "bitcoincash:qzlg6uvceehgzgtz6phmvy8gtdqyt6vf359at4n3lq", Some(true)),
+        (Validator::CashAddr, "qzlg6uvceehgzgtz6phmvy8gtdqyt6vf359at4n3lq", Some(true)),
+        (Validator::CashAddr, "bitcoincash:qzlg6uvceehgzgtz6phmvy8gtdqyt6vf359at4n3lp", Some(false)),
+        (Validator::Eip55, "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed", Some(true)),
+        (Validator::Eip55, "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD", Some(false)),
+        (Validator::Eip55, "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed", None),
+        (Validator::Eip55, "0x5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED", None),
+        (Validator::TurkishIdentificationNumber, "10000000146", Some(true)),
+        (Validator::TurkishIdentificationNumber, "10000000147", Some(false)),
+        (Validator::ItalianFiscalCode, "RSSMRA00A01H501C", Some(true)),
+        (Validator::ItalianFiscalCode, "RSSMRA00A01H501D", Some(false)),
+    ];
+    for (validator, text, valid) in cases {
+        assert_eq!(validator.validate(text), valid, "{:?} of {}", validator, text);
+    }
+}
+
+#[test]
+fn report_checksum_on_matches() {
+    let identifier = Identifier::default()
+        .filter(Filter::default().include("credit card").unwrap().validated_only(true));
+    let mut matches: Vec<Match> = Vec::new();
+    identifier.identify_text("4111111111111111 4111111111111112", &mut matches).unwrap();
+
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].matched_on, "4111111111111111");
+    assert_eq!(matches[0].validated, Some(true));
+}
+
+#[test]
+fn keep_lowercase_ethereum_address_without_checksum() {
+    let identifier = Identifier::default()
+        .filter(Filter::default().include("ethereum").unwrap().validated_only(true));
+    let mut matches: Vec<Match> = Vec::new();
+    identifier.identify_text("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed", &mut matches).unwrap();
+
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].validated, None);
+}
+
+#[test]
+fn identify_percent_and_hex_encoded_text() {
+    let options = Options { decode_depth: 1, ..Options::default() };
+    let identifier = Identifier::default()
+        .filter(Filter::default().include("ethereum").unwrap())
+        .options(options);
+    let mut matches: Vec<Match> = Vec::new();
+    identifier.identify_text(
+        "q=%30%7852908400098527886E0F7030069857D2E4169EE7 \
+        307864316132393131623662336663323466313939303362616135376162373030653034656565383132",
+        &mut matches,
+    ).unwrap();
+
+    assert_eq!(matches.len(), 2);
+    assert_eq!(matches[0].decoded, [Encoding::Percent]);
+    assert_eq!(matches[0].location.start, 0);
+    assert_eq!(matches[1].matched_on, "0xd1a2911b6b3fc24f19903baa57ab700e04eee812");
+    assert_eq!(matches[1].decoded, [Encoding::Hex]);
+}
+
+#[test]
+fn find_embedded_file_signatures() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("firmware.bin")?;
+    let mut content: Vec<u8> = b"\x7fELF\x02\x01\x01\x00".to_vec();
+    content.extend_from_slice(&[0; 24]);
+    content.extend_from_slice(b"PK\x03\x04\x14\x00");
+    file.write_binary(&content)?;
+
+    let identifier = Identifier::default()
+        .filter(Filter::default().include("file signature").unwrap());
+    let mut matches: Vec<Match> = Vec::new();
+    identifier.identify_file(file.path(), &mut matches)?;
+
+    let found: Vec<(&str, usize)> = matches.iter()
+        .map(|m| (m.name.as_str(), m.location.start))
+        .collect();
+    assert_eq!(found, [
+        ("Executable and Linkable Format (ELF)", 0),
+        ("ZIP Archive (ZIP, JAR, DOCX, APK)", 32),
+    ]);
+
+    Ok(())
+}
+
+#[test]
+fn only_embed_distinctive_file_signatures() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("program.exe")?;
+    let mut content: Vec<u8> = b"MZ\x90\x00".to_vec();
+    content.extend_from_slice(&[0; 28]);
+    content.extend_from_slice(b"GIF89a %PDF-1.7 OggS \x89PNG\r\n\x1a\n");
+    file.write_binary(&content)?;
+
+    let identifier = Identifier::default()
+        .filter(Filter::default().include("file signature").unwrap());
+    let mut matches: Vec<Match> = Vec::new();
+    identifier.identify_file(file.path(), &mut matches)?;
+
+    let found: Vec<(&str, usize, f32)> = matches.iter()
+        .map(|m| (m.name.as_str(), m.location.start, m.rarity))
+        .collect();
+    assert_eq!(found, [
+        ("DOS/Windows Executable (EXE, DLL)", 0, 0.2),
+        ("Portable Network Graphics (PNG)", 53, 1.0),
+    ]);
+
+    Ok(())
+}
+
+#[test]
+fn rank_hash_candidates() {
+    let names = |hash: &str| -> Vec<&str> {
+        identify_hash(hash).iter().map(|c| c.name).collect()
+    };
+
+    assert_eq!(names("8743b52063cd84097a65d1633f5c74f5"), ["MD5", "NTLM", "MD4", "LM"]);
+    assert_eq!(names("31D6CFE0D16AE931B73C59D7E0C089C0"), ["NTLM", "LM", "MD5", "MD4"]);
+    assert_eq!(names("$2b$12$LhayLxezLhK1LhWvKxCyLOj0j1u.Kj0jZ0pEmm134uzrQlFvQJLF6"), ["bcrypt"]);
+    assert_eq!(names("not a hash"), Vec::<&str>::new());
+
+    let sha512crypt = identify_hash(
+        "$6$52450745$k5ka2p8bFuSmoVT1tzOyyuaREkkKBcCNqoDKzYiJL9RaE8yMnPgh2XzzF0NDrUhgrcLwg78xs1w5pJiypEdFX/"
+    );
+    assert_eq!(sha512crypt[0].hashcat_mode, Some(1800));
+    assert_eq!(sha512crypt[0].john_format, Some("sha512crypt"));
+}
+
+#[test]
+fn reach_every_hash_format_from_a_pattern() {
+    // one sample of each format `identify_hash` knows
+    let samples = [
+        ("$2a$05$LhayLxezLhK1LhWvKxCyLOj0j1u.Kj0jZ0pEmm134uzrQlFvQJLF6", "bcrypt", Some(3200)),
+        ("$1$28772684$iEwNOgGugqO9.bIz5sk8k/", "md5crypt", Some(500)),
+        ("$apr1$71850310$gh9m4xcAn3MGxogwX/ztb.", "Apache MD5 (apr1)", Some(1600)),
+        ("$5$rounds=5000$GX7BopJZJxPc/KEK$le16UF8I2Anb.rOrn22AUPWvzUETDGefUmAV8AZkGcD",
+            "sha256crypt", Some(7400)),
+        ("$6$52450745$k5ka2p8bFuSmoVT1tzOyyuaREkkKBcCNqoDKzYiJL9RaE8yMnPgh2XzzF0NDrUhgrcLwg78xs1w5pJiypEdFX/",
+            "sha512crypt", Some(1800)),
+        ("$argon2id$v=19$m=65536,t=3,p=4$c29tZXNhbHQ$RdescudvJCsgt3ub+b+dWRWJTmaaJObG",
+            "Argon2", Some(34000)),
+        ("$7$C6..../....SodiumChloride$kBGj9fHznVYFQMEn/qDCfrDevf9YDtcDdKvEqHJLV8D", "scrypt", None),
+        ("SCRYPT:1024:1:1:MDIwMzMwNTQwNDQyNQ==:5FW+zWivLxgCWj7qLiQbeC8zaNQ+qdO0NUinvqyFcfo=",
+            "scrypt", Some(8900)),
+        ("pbkdf2_sha256$20000$H0dPx8NeajVu$GiC4k5kqbbR9qWBlsRgDywNqC2vd9kqfk7zdorEnNas=",
+            "Django (PBKDF2-SHA256)", Some(10000)),
+        ("sha1$fe76b$02d5916550edf7fc8c886f044887f4b1abf9b013", "Django (SHA-1)", Some(124)),
+        ("$pbkdf2-sha256$29000$c2FsdHNhbHRzYWx0MTIzNA$G4r08gDYYblyXHK2tJy0BhwEat3.P9uqtF87X43ZN74",
+            "PBKDF2-HMAC-SHA256 (passlib)", Some(20300)),
+        ("$pbkdf2-sha512$29000$c2FsdHNhbHRzYWx0MTIzNA$zvKJwinutGWMu5cygbZoxRRinFhkQj1IbROzShD7NVYl9MdB6hBMRWqYVgCeA.lQFthSbrXHq2rJG.M9lP/ctw",
+            "PBKDF2-HMAC-SHA512 (passlib)", Some(20200)),
+        ("$pbkdf2$29000$c2FsdHNhbHRzYWx0MTIzNA$u0WLSfbJ08GO3PiArAa1kGqb/pg",
+            "PBKDF2-HMAC-SHA1 (passlib)", Some(20400)),
+        ("sha256:1000:MTc3MTA0MTQwMjQxNzY=:PYjCU215Mi57AYPKva9j7mvF4Rc5bCnt",
+            "PBKDF2-HMAC-SHA256", Some(10900)),
+        ("$P$984478476IagS59wHZvyQMArzfx58u.", "phpass (WordPress, phpBB3)", Some(400)),
+        ("$S$C33783772bRXEx1aCsvY.dqgaaSu76XmVlKrW9Qu8IQlvxHlmzLf", "Drupal 7", Some(7900)),
+        ("*FCF7C1B8749CF99D88E5F34271D636178FB5D130", "MySQL 4.1+", Some(300)),
+        ("cbf43926", "CRC32", Some(11500)),
+        ("5d2e19393cc5ef67", "MySQL323", Some(200)),
+    ];
+
+    let identifier = Identifier::default()
+        .filter(Filter::default().rarity("0:1").unwrap().include("hash").unwrap());
+    for (sample, name, hashcat_mode) in samples {
+        let mut matches: Vec<Match> = Vec::new();
+        identifier.identify_text(sample, &mut matches).unwrap();
+
+        let m = matches.iter().find(|m| m.matched_on == sample)
+            .unwrap_or_else(|| panic!("no pattern matches {}", sample));
+        assert_eq!(m.hashes[0].name, name, "{}", sample);
+        assert_eq!(m.hashes[0].hashcat_mode, hashcat_mode, "{}", sample);
+    }
+
+    // salts md5crypt doesn't allow aren't matched as a hash without candidates
+    let mut matches: Vec<Match> = Vec::new();
+    identifier.identify_text("$1$saltsaltsalt$iEwNOgGugqO9.bIz5sk8k/", &mut matches).unwrap();
+    assert!(matches.iter().all(|m| !m.hashes.is_empty()));
+}
+
+#[test]
+fn decode_details_of_matches() {
+    let identifier = Identifier::default()
+        .filter(Filter::default().include("jwt,uuid").unwrap());
+    let mut matches: Vec<Match> = Vec::new();
+    // unsigned token with {"alg":"none"} and {"sub":"admin","exp":1600000000}
+    identifier.identify_text(
+        "eyJhbGciOiJub25lIn0.eyJzdWIiOiJhZG1pbiIsImV4cCI6MTYwMDAwMDAwMH0.\
+        AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA 017f22e2-79b0-7cc3-98c4-dc0c0c07398f",
+        &mut matches,
+    ).unwrap();
+
+    let Some(Details::Jwt { header, claims, flags }) = &matches[0].details else {
+        panic!("no JWT details: {:?}", matches[0].details);
+    };
+    assert_eq!(header["alg"], "none");
+    assert_eq!(claims["sub"], "admin");
+    assert_eq!(flags, &["alg: none", "expired"]);
+
+    assert_eq!(matches[1].details, Some(Details::Uuid {
+        version: 7,
+        variant: "RFC 9562",
+        time: Some(String::from("2022-02-22T19:22:22.000Z")),
+    }));
+}
+
+#[test]
+fn skip_details_of_custom_uuid_pattern_matching_non_ascii() {
+    let mut patterns = CustomPatterns::default();
+    patterns.load_str(r#"[
+        { "name": "Loose UUID", "regex": "^é{16}$", "rarity": 1, "tags": ["UUID"] }
+    ]"#).unwrap();
+    let identifier = Identifier::default()
+        .filter(Filter::default().include("uuid").unwrap())
+        .patterns(patterns);
+    let mut matches: Vec<Match> = Vec::new();
+    // 32 bytes, like the hex digits of a UUID
+    identifier.identify_text("éééééééééééééééé", &mut matches).unwrap();
+
+    let m = matches.iter().find(|m| m.name == "Loose UUID").unwrap();
+    assert_eq!(m.details, None);
+}
+
+#[test]
+fn filter_packets_by_protocol_port_and_host() {
+    let packet = Packet {
+        number: 1,
+        timestamp: None,
+        protocol: Some(Protocol::Udp),
+        src: Some("192.168.0.2".parse().unwrap()),
+        dst: Some("2001:db8::1".parse().unwrap()),
+        src_port: Some(5353),
+        dst_port: Some(53),
+    };
+    let undissected = Packet {
+        protocol: None, src: None, dst: None, src_port: None, dst_port: None, ..packet.clone()
+    };
+
+    assert!(!Filter::default().gets_excluded_packet(&undissected));
+    let filter = Filter::default().protocols("tcp,UDP").unwrap().ports("53").unwrap()
+        .hosts("2001:db8::1").unwrap();
+    assert!(!filter.gets_excluded_packet(&packet));
+    assert!(filter.gets_excluded_packet(&undissected));
+    assert!(Filter::default().protocols("tcp").unwrap().gets_excluded_packet(&packet));
+    assert!(Filter::default().ports("80,443").unwrap().gets_excluded_packet(&packet));
+    assert!(Filter::default().hosts("10.0.0.1").unwrap().gets_excluded_packet(&packet));
+    assert!(Filter::default().protocols("sctp").is_err());
+    assert!(Filter::default().ports("http").is_err());
+}
+
+#[test]
+fn identify_values_of_structured_files_with_key_paths() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("prod.yaml")?;
+    file.write_str("database:\n  host: db.example.com\n  session_token: 01ARZ3NDEKTSV4RRFFQ69G5FAV\n\
+        build_id: 01ARZ3NDEKTSV4RRFFQ69G5FAW\n")?;
+
+    let mut matches: Vec<Match> = Vec::new();
+    Identifier::default().identify_file(file.path(), &mut matches)?;
+    let url = matches.iter().find(|m| m.matched_on == "db.example.com").unwrap();
+    assert_eq!(url.key_path.as_deref(), Some("database.host"));
+    assert_eq!((url.location.line, url.location.column), (2, 9));
+
+    // too common to be reported, unless the key says it's a secret
+    let ulid = matches.iter().find(|m| m.name == "ULID").unwrap();
+    assert_eq!(ulid.key_path.as_deref(), Some("database.session_token"));
+    assert!(ulid.rarity >= 0.5);
+    assert!(!matches.iter().any(|m| m.matched_on.ends_with("5FAW")));
+
+    Ok(())
+}
+
+#[test]
+fn identify_unescaped_values_of_structured_files() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("wallets.json")?;
+    file.write_str(concat!(
+        r#"{"api_keys": {"escaped": "\u0030x52908400098527886E0F7030069857D2E4169EE7","#,
+        "\n",
+        r#" "quoted": "é\t\"0xde0B295669a9FD93d5F28D9Ec85E40f4cb697BAe\""}}"#,
+    ))?;
+
+    let mut matches: Vec<Match> = Vec::new();
+    Identifier::default().identify_file(file.path(), &mut matches)?;
+    let escaped = matches.iter()
+        .find(|m| m.matched_on == "0x52908400098527886E0F7030069857D2E4169EE7")
+        .unwrap();
+    assert_eq!(escaped.key_path.as_deref(), Some("api_keys.escaped"));
+    // from the escape sequence written for its first character
+    assert_eq!((escaped.location.start, escaped.location.end), (26, 73));
+    let quoted = matches.iter()
+        .find(|m| m.matched_on == "0xde0B295669a9FD93d5F28D9Ec85E40f4cb697BAe")
+        .unwrap();
+    assert_eq!(quoted.key_path.as_deref(), Some("api_keys.quoted"));
+    assert_eq!((quoted.location.line, quoted.location.column), (2, 18));
+
+    Ok(())
+}
+
+#[test]
+fn identify_multi_line_yaml_scalars() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("notes.yaml")?;
+    file.write_str("literal: |\n  first line\n  wallet 0x52908400098527886E0F7030069857D2E4169EE7\n\
+        folded: >-\n  some\n  0xde0B295669a9FD93d5F28D9Ec85E40f4cb697BAe\n\
+        list:\n  - \"a \\\n    1J7mdg5rbQyUHENYdx39WVWK7fsLpEoXZy\"\n")?;
+
+    let mut matches: Vec<Match> = Vec::new();
+    Identifier::default().identify_file(file.path(), &mut matches)?;
+    let find = |text: &str| matches.iter().find(|m| m.matched_on == text).unwrap();
+    let literal = find("0x52908400098527886E0F7030069857D2E4169EE7");
+    assert_eq!(literal.key_path.as_deref(), Some("literal"));
+    assert_eq!((literal.location.line, literal.location.column), (3, 10));
+    let folded = find("0xde0B295669a9FD93d5F28D9Ec85E40f4cb697BAe");
+    assert_eq!(folded.key_path.as_deref(), Some("folded"));
+    assert_eq!((folded.location.line, folded.location.column), (6, 3));
+    let quoted = find("1J7mdg5rbQyUHENYdx39WVWK7fsLpEoXZy");
+    assert_eq!(quoted.key_path.as_deref(), Some("list[0]"));
+    assert_eq!((quoted.location.line, quoted.location.column), (9, 5));
+
+    Ok(())
+}
+
+#[test]
+fn keep_boosted_rarity_of_secret_values_in_filter_range() -> Result<(), Box<dyn std::error::Error>> {
+    let file = assert_fs::NamedTempFile::new("prod.yaml")?;
+    file.write_str("session_token: 01ARZ3NDEKTSV4RRFFQ69G5FAV\n")?;
+
+    let mut matches: Vec<Match> = Vec::new();
+    Identifier::default()
+        .filter(Filter::default().rarity("0:0.4")?)
+        .identify_file(file.path(), &mut matches)?;
+    assert!(matches.iter().all(|m| m.rarity <= 0.4));
+    assert!(!matches.iter().any(|m| m.name == "ULID"));
+
+    Ok(())
+}